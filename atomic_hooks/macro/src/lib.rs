@@ -12,10 +12,36 @@ use quote::{format_ident, quote};
 use syn::{AttributeArgs};
 
 
+// `reversible` records in the global undo history, `reversible = "name"` in
+// the named undo scope
+#[derive(Debug)]
+enum Reversible {
+    Global,
+    Scope(String),
+}
+
+impl FromMeta for Reversible {
+    fn from_word() -> darling::Result<Self> {
+        Ok(Reversible::Global)
+    }
+
+    fn from_string(value: &str) -> darling::Result<Self> {
+        Ok(Reversible::Scope(value.to_string()))
+    }
+}
+
 #[derive(Debug, FromMeta)]
 struct MacroArgs {
     #[darling(default)]
-    reversible: bool,
+    reversible: Option<Reversible>,
+    #[darling(default)]
+    memo: bool,
+    #[darling(default)]
+    keep_alive: bool,
+    #[darling(default)]
+    persist: Option<String>,
+    #[darling(default)]
+    family: bool,
 }
 
 #[derive(Debug, FromMeta)]
@@ -24,6 +50,10 @@ struct ReactionMacroArgs {
     existing_state: bool,
     #[darling(default)]
     suspended:bool,
+    #[darling(default)]
+    lazy: bool,
+    #[darling(default)]
+    memo: bool,
 }
 
 
@@ -44,13 +74,14 @@ pub fn atom(args: TokenStream, input: TokenStream) -> TokenStream {
 
     
 
-    let atom_fn_ident = if args.reversible {
+    let atom_fn_ident = if args.reversible.is_some() {
         format_ident!("atom_reverse")
     }  else {
         format_ident!("atom")
     };
     
     let sig = input_fn.sig.clone();
+    let fn_ident = input_fn.sig.ident.clone();
     
 
     let the_outer_type = match input_fn.sig.output {
@@ -60,7 +91,7 @@ pub fn atom(args: TokenStream, input: TokenStream) -> TokenStream {
 
 
     let the_type = 
-    if args.reversible {
+    if args.reversible.is_some() {
         match *the_outer_type {
             syn::Type::Path(p) => {
                 
@@ -149,23 +180,100 @@ pub fn atom(args: TokenStream, input: TokenStream) -> TokenStream {
         }
     }
 
-    let hash_quote = quote!( (CallSite::here(), #template_quote) );
-    
+    let args_quote = quote!( (#template_quote) );
 
 
-    let set_inert_with_reverse = if args.reversible {
+    let set_inert_with_reverse = if args.reversible.is_some() {
         quote!( set_inert_atom_reversible_state_with_id::<#the_type>(value,__id ); )
      } else {
         quote!( set_inert_atom_state_with_id::<#the_type>(value,__id );)
      };
 
+    let undo_scope = if let Some(Reversible::Scope(name)) = &args.reversible {
+        quote!( set_undo_scope(__id, #name); )
+    } else {
+        quote!()
+    };
+
+    let memoize = if args.memo {
+        quote!( memoize_reactive_state_with_id::<#the_type>(__id); )
+    } else {
+        quote!()
+    };
+
+    let keep_alive = if args.keep_alive {
+        quote!( keep_reactive_state_alive_with_id(__id); )
+    } else {
+        quote!()
+    };
+
+    let (initial_value, persist) = if let Some(key) = &args.persist {
+        (
+            quote!( match take_persisted_value::<#the_type>(__id, #key) {
+                Some(value) => value,
+                None => {#body},
+            }),
+            quote!( persist_atom_with_id::<#the_type>(__id, #key); ),
+        )
+    } else {
+        (quote!({#body}), quote!())
+    };
+
+    let family_ident = format_ident!("{}_family", fn_ident);
+    let (family_key, register_family_member, family_fn) = if args.family {
+        if args.reversible.is_some() {
+            panic!("A family atom cannot be reversible");
+        }
+        let arg_idents = input_fn.sig.inputs.iter().map(|input| format_ident!("{}",get_arg_name(input))).collect::<Vec<_>>();
+        let arg_types = input_fn.sig.inputs.iter().map(|input| match input {
+            FnArg::Typed(t) => t.ty.clone(),
+            FnArg::Receiver(_) => panic!("cannot be a method with self receiver"),
+        }).collect::<Vec<_>>();
+        // a single argument is its own key, several are a tuple
+        let (key_type, key_value) = match arg_idents.len() {
+            0 => panic!("A family atom needs at least one argument to key its members"),
+            1 => {
+                let (ident, the_type) = (&arg_idents[0], &arg_types[0]);
+                (quote!(#the_type), quote!(#ident.clone()))
+            },
+            _ => (quote!( (#(#arg_types),*) ), quote!( (#(#arg_idents.clone()),*) )),
+        };
+        let key_pattern = if arg_idents.len() == 1 {
+            quote!(#(#arg_idents)*)
+        } else {
+            quote!( (#(#arg_idents),*) )
+        };
+        (
+            quote!( let __family_key = #key_value; ),
+            quote!( #family_ident().register_member(__family_key, __id); ),
+            quote!(
+                #vis fn #family_ident() -> AtomFamily<#key_type, #the_type> {
+                    AtomFamily::new(
+                        concat!(module_path!(), "::", stringify!(#fn_ident), "::family"),
+                        |__key: #key_type| {
+                            let #key_pattern = __key;
+                            #fn_ident(#(#arg_idents),*)
+                        },
+                    )
+                }
+            ),
+        )
+    } else {
+        (quote!(), quote!(), quote!())
+    };
 
     
     quote!(
 
+       #family_fn
+
        #vis #sig{
 
-                let __id  = return_key_for_type_and_insert_if_required(#hash_quote);
+                let __id  = return_key_for_name_and_insert_if_required(
+                    concat!(module_path!(), "::", stringify!(#fn_ident)),
+                    #args_quote,
+                );
+                #family_key
 
                 let func = move || {
                     #use_args_quote
@@ -178,7 +286,7 @@ pub fn atom(args: TokenStream, input: TokenStream) -> TokenStream {
                             let context = ReactiveContext::new(__id );
                             illicit::Layer::new().offer(std::cell::RefCell::new(context) ).enter(|| {
                                 
-                                let value = {#body};
+                                let value = #initial_value;
                                 #set_inert_with_reverse
                             })
 
@@ -187,7 +295,27 @@ pub fn atom(args: TokenStream, input: TokenStream) -> TokenStream {
                     
                 };
 
-                #atom_fn_ident::<#the_type,_>(__id ,func)
+                #undo_scope
+                #memoize
+                #keep_alive
+                {
+                    // only atoms of a Clone type are captured by snapshot()
+                    use RegisterSnapshot as _;
+                    use SkipSnapshot as _;
+                    (&SnapshotRegistrar::<#the_type>::new(__id)).register_snapshot();
+                    // only atoms of a serializable type are hydrated and dehydrated
+                    use RegisterHydrate as _;
+                    use SkipHydrate as _;
+                    (&HydrateRegistrar::<#the_type>::new(__id)).register_hydrate();
+                    // only atoms of a Debug type show their values to write middleware
+                    use RegisterDebug as _;
+                    use SkipDebug as _;
+                    (&DebugRegistrar::<#the_type>::new()).register_debug();
+                }
+                let atom = #atom_fn_ident::<#the_type,_>(__id ,func);
+                #persist
+                #register_family_member
+                atom
             
         } 
 
@@ -220,6 +348,8 @@ pub fn reaction(args: TokenStream, input: TokenStream) -> TokenStream {
 
     let reaction_suspended_ident = if args.suspended {
         format_ident!("reaction_start_suspended")
+    } else if args.lazy {
+        format_ident!("reaction_lazy")
     } else {
         format_ident!("reaction")
     };
@@ -231,6 +361,7 @@ pub fn reaction(args: TokenStream, input: TokenStream) -> TokenStream {
     
     let sig = input_fn.sig.clone();
     let vis = input_fn.vis.clone();
+    let fn_ident = input_fn.sig.ident.clone();
 
     let the_outer_type = match input_fn.sig.output.clone() {
         syn::ReturnType::Default => panic!("Your atom MUST return a non-Unit value"),
@@ -302,7 +433,13 @@ pub fn reaction(args: TokenStream, input: TokenStream) -> TokenStream {
         }
     }
 
-    let hash_quote = quote!( (CallSite::here(), #template_quote) );
+    let args_quote = quote!( (#template_quote) );
+
+    let memoize = if args.memo {
+        quote!( memoize_reactive_state_with_id::<#the_type>(__id); )
+    } else {
+        quote!()
+    };
 
     let use_existing_state = if args.existing_state {
         quote!(
@@ -319,43 +456,25 @@ pub fn reaction(args: TokenStream, input: TokenStream) -> TokenStream {
             #vis #sig{
 
 
-                    let __id = return_key_for_type_and_insert_if_required(#hash_quote);
+                    let __id = return_key_for_name_and_insert_if_required(
+                        concat!(module_path!(), "::", stringify!(#fn_ident)),
+                        #args_quote,
+                    );
 
                
                     if !reactive_state_exists_for_id::<#the_type>(__id ){
                
                         let func = move || {
                             #use_args_quote
-                            
-                            
-                                
-                            
-                            
-                            topo::root(||{
-                                
-                            let mut context = ReactiveContext::new(__id );
-                            {
-                            
-                            illicit::Layer::new().offer(std::cell::RefCell::new(context) ).enter(|| {
-                                
-                                
+                            // dependencies are re-tracked on every run
+                            run_in_reactive_context(__id, || {
                                 #use_existing_state
                                 let value = {#body};
                                 set_inert_atom_state_with_id::<#the_type>(value,__id );
-                                // we need to remove dependencies that do nto exist anymore
-                                unlink_dead_links(__id );
                             })
-                            
-                        }
-                        })
-                        
-                    
-                    
-
-
                         };
                         
-
+                        #memoize
                         #reaction_suspended_ident::<#the_type,_>(__id ,func)
                     } else {
                         Reaction::<#the_type>::new(__id )                 
@@ -369,3 +488,106 @@ pub fn reaction(args: TokenStream, input: TokenStream) -> TokenStream {
     quote.into()
 }
 
+
+#[derive(Debug, FromMeta)]
+struct SelectorMacroArgs {
+    set: String,
+    #[darling(default)]
+    memo: bool,
+}
+
+#[proc_macro_attribute]
+pub fn selector(args: TokenStream, input: TokenStream) -> TokenStream {
+    let attr_args = syn::parse_macro_input!(args as AttributeArgs);
+
+    let args = match SelectorMacroArgs::from_list(&attr_args){
+        Ok(v) => v,
+        Err(e) => panic!("{}",e),
+    };
+
+    let setter: syn::Path = match syn::parse_str(&args.set) {
+        Ok(path) => path,
+        Err(e) => panic!("set should name the setter function: {}", e),
+    };
+
+    let input_fn: ItemFn = syn::parse_macro_input!(input);
+
+    let sig = input_fn.sig.clone();
+    let vis = input_fn.vis.clone();
+    let fn_ident = input_fn.sig.ident.clone();
+
+    let the_outer_type = match input_fn.sig.output.clone() {
+        syn::ReturnType::Default => panic!("Your selector MUST return a non-Unit value"),
+        syn::ReturnType::Type(_, the_type) => the_type.clone(),
+    };
+
+    let the_type = match *the_outer_type {
+        syn::Type::Path(p) => {
+            if let Some(selector_segment) = p.path.segments.first() {
+                if selector_segment.ident.to_string() != "Selector" {
+                    panic!("You really need to return a Selector wrapped type");
+                }
+                match &selector_segment.arguments {
+                        syn::PathArguments::AngleBracketed(angle_brack_args) => {
+                            let first_arg = angle_brack_args.args.first().expect("Selector should have a first type");
+                            if let syn::GenericArgument::Type(a_type) = first_arg {
+                                a_type.clone()
+                            } else {
+                                panic!("Selector doest hold a type")
+                            }
+                        },
+                        _ => panic!("Selector has no type???")
+                }
+            } else {
+                panic!("You do need to return a Selector wrapped type");
+            }
+        },
+        _ => panic!("You need to return a Selector wrapped type"),
+    };
+
+    let body = input_fn.block.clone();
+
+    let arg_idents = input_fn.sig.inputs.iter().map(|input| format_ident!("{}",get_arg_name(input))).collect::<Vec<_>>();
+    let args_quote = quote!( (#(#arg_idents.clone(),)*) );
+    let use_args_quote = quote!( #(let #arg_idents = #arg_idents.clone();)* );
+
+    let memoize = if args.memo {
+        quote!( memoize_reactive_state_with_id::<#the_type>(__id); )
+    } else {
+        quote!()
+    };
+
+    quote!(
+
+        #vis #sig{
+
+                let __id = return_key_for_name_and_insert_if_required(
+                    concat!(module_path!(), "::", stringify!(#fn_ident)),
+                    #args_quote,
+                );
+
+                if !reactive_state_exists_for_id::<#the_type>(__id ){
+
+                    // the setter is called with the selector's arguments and the new value
+                    let set = {
+                        #use_args_quote
+                        move |value: #the_type| #setter(#(#arg_idents.clone(),)* value)
+                    };
+                    register_selector_setter::<#the_type,_>(__id, set);
+
+                    let func = move || {
+                        #use_args_quote
+                        run_in_reactive_context(__id, || {
+                            let value = {#body};
+                            set_inert_atom_state_with_id::<#the_type>(value,__id );
+                        })
+                    };
+
+                    #memoize
+                    reaction::<#the_type,_>(__id ,func);
+                }
+                Selector::<#the_type>::new(__id )
+        }
+
+    ).into()
+}
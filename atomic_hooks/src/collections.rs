@@ -0,0 +1,773 @@
+use crate::{
+    helpers::CallSite,
+    reactive_state_access::{
+        atom::Atom,
+        effect::{effect, Effect, Subscription},
+        observable::Observable,
+        CloneReactiveState,
+    },
+    reactive_state_functions::{
+        atom, batch, clone_reactive_state_with_id, reactive_state_exists_for_id,
+        set_inert_atom_state_with_id, with_store,
+    },
+    store::{SlottedKey, StorageKey},
+};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{hash_map::DefaultHasher, VecDeque},
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    rc::Rc,
+};
+
+/// A change made to a [`ReactiveVec`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum VecDiff<T> {
+    Insert { index: usize, value: T },
+    Update { index: usize, value: T },
+    Remove { index: usize },
+    Move { from: usize, to: usize },
+    Clear,
+}
+
+/// A change made to a [`ReactiveMap`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum MapDiff<K, V> {
+    Insert { key: K, value: V },
+    Update { key: K, value: V },
+    Remove { key: K },
+    Clear,
+}
+
+// the diffs of a collection that not every subscriber has seen yet
+struct DiffJournal<D> {
+    first_seq: u64,
+    diffs: VecDeque<D>,
+    cursors: Vec<(Effect, Rc<Cell<u64>>)>,
+}
+
+impl<D: Clone> DiffJournal<D> {
+    fn next_seq(&self) -> u64 {
+        self.first_seq + self.diffs.len() as u64
+    }
+
+    // returns the diffs after `cursor` and drops those every subscriber has seen
+    fn take(&mut self, cursor: &Cell<u64>) -> Vec<D> {
+        let skip = (cursor.get() - self.first_seq) as usize;
+        let diffs = self.diffs.iter().skip(skip).cloned().collect();
+        cursor.set(self.next_seq());
+
+        let oldest_unseen = self
+            .cursors
+            .iter()
+            .map(|(_, cursor)| cursor.get())
+            .min()
+            .unwrap_or_else(|| self.next_seq());
+        while self.first_seq < oldest_unseen {
+            self.diffs.pop_front();
+            self.first_seq += 1;
+        }
+        diffs
+    }
+}
+
+type SharedJournal<D> = Rc<RefCell<DiffJournal<D>>>;
+
+// the node observed by diff subscribers, bumped on every recorded diff
+fn diff_version_id(id: StorageKey) -> StorageKey {
+    let mut hasher = DefaultHasher::new();
+    id.hash(&mut hasher);
+    "diffs".hash(&mut hasher);
+    StorageKey::SlottedKey(SlottedKey {
+        location: hasher.finish(),
+        slot: 0,
+    })
+}
+
+// the location shared by the atoms of the items of a collection
+fn item_location(id: StorageKey) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    id.hash(&mut hasher);
+    hasher.finish()
+}
+
+// creates the atom for `id` holding `value`, unless it exists. A collection
+// owns its atoms, they are kept by garbage collection until it removes them
+fn init_atom<T: 'static + Clone>(id: StorageKey, value: T) {
+    with_store(|store_refcell| {
+        let mut store = store_refcell.borrow_mut();
+        store.insert_seen_id(id);
+        store.kept_alive_ids.insert(id);
+    });
+    atom::<T, _>(id, move || set_inert_atom_state_with_id(value.clone(), id));
+}
+
+// removes the collection `id` along with the atoms of its items
+fn delete_collection(id: StorageKey, item_ids: Vec<StorageKey>) {
+    with_store(|store_refcell| {
+        let mut store = store_refcell.borrow_mut();
+        for item_id in item_ids.iter().chain(&[diff_version_id(id), id]) {
+            store.remove_id(item_id);
+        }
+    });
+}
+
+#[track_caller]
+fn id_at_call_site() -> StorageKey {
+    let mut hasher = DefaultHasher::new();
+    CallSite::here().hash(&mut hasher);
+    StorageKey::SlottedKey(SlottedKey {
+        location: hasher.finish(),
+        slot: 0,
+    })
+}
+
+// every call creates a new collection, even from the same location
+#[track_caller]
+fn new_id() -> StorageKey {
+    let mut hasher = DefaultHasher::new();
+    CallSite::here().hash(&mut hasher);
+    let slot = with_store(|store_refcell| {
+        let mut store = store_refcell.borrow_mut();
+        store.next_collection_slot += 1;
+        store.next_collection_slot
+    });
+    StorageKey::SlottedKey(SlottedKey {
+        location: hasher.finish(),
+        slot,
+    })
+}
+
+fn init_collection<D: 'static, K: 'static + Clone>(id: StorageKey) {
+    if reactive_state_exists_for_id::<SharedJournal<D>>(id) {
+        return;
+    }
+    init_atom(id, Vec::<K>::new());
+    init_atom(diff_version_id(id), 0_u64);
+    let journal = DiffJournal::<D> {
+        first_seq: 0,
+        diffs: VecDeque::new(),
+        cursors: vec![],
+    };
+    set_inert_atom_state_with_id(Rc::new(RefCell::new(journal)), id);
+}
+
+fn journal<D: 'static>(id: StorageKey) -> SharedJournal<D> {
+    clone_reactive_state_with_id::<SharedJournal<D>>(id).expect("collection should be present")
+}
+
+// diffs are only kept while there are subscribers to deliver them to
+fn record_diff<D: 'static + Clone>(id: StorageKey, diff: D) {
+    let journal = journal::<D>(id);
+    journal
+        .borrow_mut()
+        .cursors
+        .retain(|(effect, _)| effect.is_active());
+    if journal.borrow().cursors.is_empty() {
+        return;
+    }
+    journal.borrow_mut().diffs.push_back(diff);
+    Atom::<u64>::new(diff_version_id(id)).update(|version| *version += 1);
+}
+
+#[track_caller]
+fn subscribe_diffs<D: 'static + Clone, F: Fn(&D) + 'static>(
+    id: StorageKey,
+    func: F,
+) -> Subscription {
+    let journal = journal::<D>(id);
+    let cursor = Rc::new(Cell::new(journal.borrow().next_seq()));
+    let version = Atom::<u64>::new(diff_version_id(id));
+
+    let effect_cursor = cursor.clone();
+    let effect_journal = journal.clone();
+    let effect = effect(move || {
+        version.observe();
+        let diffs = effect_journal.borrow_mut().take(&effect_cursor);
+        for diff in &diffs {
+            func(diff);
+        }
+    });
+    journal.borrow_mut().cursors.push((effect, cursor));
+    Subscription::new(effect)
+}
+
+/// A list whose items are each stored in their own atom, so that changing
+/// one item only re-runs what observes that item, and whose changes can be
+/// subscribed to as [`VecDiff`]s.
+///
+/// Each item keeps its atom when other items are inserted, removed or moved,
+/// so its id can be used to key rendered views.
+///
+/// ```
+/// use atomic_hooks::*;
+/// let todos = ReactiveVec::<String>::new();
+/// let diffs = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+/// let seen = diffs.clone();
+/// let _subscription = todos.on_diff(move |diff| seen.borrow_mut().push(diff.clone()));
+///
+/// todos.push("write docs".to_string());
+/// todos.set(0, "write more docs".to_string());
+/// assert_eq!(
+///     *diffs.borrow(),
+///     vec![
+///         VecDiff::Insert { index: 0, value: "write docs".to_string() },
+///         VecDiff::Update { index: 0, value: "write more docs".to_string() },
+///     ]
+/// );
+/// ```
+pub struct ReactiveVec<T> {
+    pub id: StorageKey,
+    _phantom_data_stored_type: PhantomData<T>,
+}
+
+impl<T> std::fmt::Debug for ReactiveVec<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({:#?})", self.id)
+    }
+}
+
+impl<T> Clone for ReactiveVec<T> {
+    fn clone(&self) -> ReactiveVec<T> {
+        *self
+    }
+}
+
+impl<T> Copy for ReactiveVec<T> {}
+
+impl<T: 'static + Clone> Default for ReactiveVec<T> {
+    #[track_caller]
+    fn default() -> Self {
+        ReactiveVec::new()
+    }
+}
+
+// the next slot for an item atom of a collection
+#[derive(Clone, Copy)]
+struct NextItemSlot(u64);
+
+impl<T> ReactiveVec<T>
+where
+    T: 'static + Clone,
+{
+    /// A new, empty vec, which is kept until it is [`delete`](Self::delete)d.
+    ///
+    /// Every call creates another vec, so this does not belong in code that
+    /// runs on every render, use [`at_call_site`](Self::at_call_site) there.
+    #[track_caller]
+    pub fn new() -> ReactiveVec<T> {
+        ReactiveVec::with_id(new_id())
+    }
+
+    /// The vec at the calling location, created empty if it does not exist
+    /// yet. Every call from that location returns the same vec.
+    #[track_caller]
+    pub fn at_call_site() -> ReactiveVec<T> {
+        ReactiveVec::with_id(id_at_call_site())
+    }
+
+    pub fn with_id(id: StorageKey) -> ReactiveVec<T> {
+        init_collection::<VecDiff<T>, StorageKey>(id);
+        ReactiveVec {
+            id,
+            _phantom_data_stored_type: PhantomData,
+        }
+    }
+
+    fn structure(&self) -> Atom<Vec<StorageKey>> {
+        Atom::new(self.id)
+    }
+
+    fn new_item(&self, value: T) -> StorageKey {
+        let slot = clone_reactive_state_with_id::<NextItemSlot>(self.id).map_or(1, |slot| slot.0);
+        set_inert_atom_state_with_id(NextItemSlot(slot + 1), self.id);
+
+        let item_id = StorageKey::SlottedKey(SlottedKey {
+            location: item_location(self.id),
+            slot,
+        });
+        init_atom(item_id, value);
+        item_id
+    }
+
+    pub fn len(&self) -> usize {
+        self.structure().get_with(Vec::len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<T> {
+        self.item(index).map(|item| item.get())
+    }
+
+    /// The atom holding the item at `index`.
+    pub fn item(&self, index: usize) -> Option<Atom<T>> {
+        self.structure()
+            .get_with(|items| items.get(index).copied())
+            .map(Atom::new)
+    }
+
+    /// The atom of every item.
+    pub fn items(&self) -> Vec<Atom<T>> {
+        self.structure().get().into_iter().map(Atom::new).collect()
+    }
+
+    pub fn to_vec(&self) -> Vec<T> {
+        self.items().into_iter().map(|item| item.get()).collect()
+    }
+
+    /// Observes the items being inserted, removed or moved, but not changes
+    /// to their values. Observe the returned atoms for those.
+    pub fn observe_items(&self) -> Vec<Atom<T>> {
+        self.structure()
+            .observe()
+            .into_iter()
+            .map(Atom::new)
+            .collect()
+    }
+
+    /// Observes every change to the vec.
+    pub fn observe(&self) -> Vec<T> {
+        self.observe_items()
+            .into_iter()
+            .map(|item| item.observe())
+            .collect()
+    }
+
+    pub fn push(&self, value: T) {
+        self.insert(self.len(), value)
+    }
+
+    /// Panics if `index > len`.
+    pub fn insert(&self, index: usize, value: T) {
+        batch(|| {
+            let item_id = self.new_item(value.clone());
+            self.structure()
+                .update(|items| items.insert(index, item_id));
+            record_diff(self.id, VecDiff::Insert { index, value });
+        })
+    }
+
+    /// Panics if `index` is out of bounds.
+    pub fn set(&self, index: usize, value: T) {
+        let item = self.item(index).expect("index out of bounds");
+        batch(|| {
+            item.set(value.clone());
+            record_diff(self.id, VecDiff::Update { index, value });
+        })
+    }
+
+    /// Panics if `index` is out of bounds.
+    pub fn update<F: FnOnce(&mut T)>(&self, index: usize, func: F) {
+        let mut value = self.get(index).expect("index out of bounds");
+        func(&mut value);
+        self.set(index, value)
+    }
+
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&self, index: usize) -> T {
+        let item = self.item(index).expect("index out of bounds");
+        let value = item.get();
+        batch(|| {
+            self.structure().update(|items| {
+                items.remove(index);
+            });
+            with_store(|store_refcell| store_refcell.borrow_mut().remove_id(&item.id));
+            record_diff(self.id, VecDiff::<T>::Remove { index });
+        });
+        value
+    }
+
+    /// Moves the item at `from` so that it ends up at `to`, keeping its atom.
+    ///
+    /// Panics if either index is out of bounds.
+    pub fn move_item(&self, from: usize, to: usize) {
+        batch(|| {
+            self.structure().update(|items| {
+                let item = items.remove(from);
+                items.insert(to, item);
+            });
+            record_diff(self.id, VecDiff::<T>::Move { from, to });
+        })
+    }
+
+    pub fn clear(&self) {
+        batch(|| {
+            for item in self.items() {
+                with_store(|store_refcell| store_refcell.borrow_mut().remove_id(&item.id));
+            }
+            self.structure().set(vec![]);
+            record_diff(self.id, VecDiff::<T>::Clear);
+        })
+    }
+
+    /// Removes the vec and the atoms of its items from the store.
+    pub fn delete(self) {
+        delete_collection(self.id, self.structure().get());
+    }
+
+    /// Calls `func` with every change made after subscribing, in order, once
+    /// propagation has completed, until the returned [`Subscription`] is
+    /// dropped.
+    #[track_caller]
+    pub fn on_diff<F: Fn(&VecDiff<T>) + 'static>(&self, func: F) -> Subscription {
+        subscribe_diffs(self.id, func)
+    }
+}
+
+/// A map whose entries are each stored in their own atom, so that changing
+/// one entry only re-runs what observes that entry, and whose changes can be
+/// subscribed to as [`MapDiff`]s. Keys are kept in insertion order.
+///
+/// ```
+/// use atomic_hooks::*;
+/// let scores = ReactiveMap::<String, u32>::new();
+/// scores.insert("ada".to_string(), 3);
+/// scores.update(&"ada".to_string(), |score| *score += 1);
+/// assert_eq!(scores.get(&"ada".to_string()), Some(4));
+/// ```
+pub struct ReactiveMap<K, V> {
+    pub id: StorageKey,
+    _phantom_data_stored_type: PhantomData<(K, V)>,
+}
+
+impl<K, V> std::fmt::Debug for ReactiveMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({:#?})", self.id)
+    }
+}
+
+impl<K, V> Clone for ReactiveMap<K, V> {
+    fn clone(&self) -> ReactiveMap<K, V> {
+        *self
+    }
+}
+
+impl<K, V> Copy for ReactiveMap<K, V> {}
+
+impl<K: 'static + Clone + Eq + Hash, V: 'static + Clone> Default for ReactiveMap<K, V> {
+    #[track_caller]
+    fn default() -> Self {
+        ReactiveMap::new()
+    }
+}
+
+impl<K, V> ReactiveMap<K, V>
+where
+    K: 'static + Clone + Eq + Hash,
+    V: 'static + Clone,
+{
+    /// A new, empty map, which is kept until it is [`delete`](Self::delete)d.
+    ///
+    /// Every call creates another map, so this does not belong in code that
+    /// runs on every render, use [`at_call_site`](Self::at_call_site) there.
+    #[track_caller]
+    pub fn new() -> ReactiveMap<K, V> {
+        ReactiveMap::with_id(new_id())
+    }
+
+    /// The map at the calling location, created empty if it does not exist
+    /// yet. Every call from that location returns the same map.
+    #[track_caller]
+    pub fn at_call_site() -> ReactiveMap<K, V> {
+        ReactiveMap::with_id(id_at_call_site())
+    }
+
+    pub fn with_id(id: StorageKey) -> ReactiveMap<K, V> {
+        init_collection::<MapDiff<K, V>, K>(id);
+        ReactiveMap {
+            id,
+            _phantom_data_stored_type: PhantomData,
+        }
+    }
+
+    fn keys_atom(&self) -> Atom<Vec<K>> {
+        Atom::new(self.id)
+    }
+
+    fn entry_id(&self, key: &K) -> StorageKey {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        StorageKey::SlottedKey(SlottedKey {
+            location: item_location(self.id),
+            slot: hasher.finish(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys_atom().get_with(Vec::len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.keys_atom().get_with(|keys| keys.contains(key))
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.entry(key).map(|entry| entry.get())
+    }
+
+    /// The atom holding the value for `key`.
+    pub fn entry(&self, key: &K) -> Option<Atom<V>> {
+        if self.contains_key(key) {
+            Some(Atom::new(self.entry_id(key)))
+        } else {
+            None
+        }
+    }
+
+    /// The keys in insertion order.
+    pub fn keys(&self) -> Vec<K> {
+        self.keys_atom().get()
+    }
+
+    /// Observes keys being inserted or removed, but not changes to their
+    /// values. Observe their [`entry`](Self::entry) for those.
+    pub fn observe_keys(&self) -> Vec<K> {
+        self.keys_atom().observe()
+    }
+
+    /// Observes every change to the map.
+    pub fn observe(&self) -> Vec<(K, V)> {
+        self.observe_keys()
+            .into_iter()
+            .map(|key| {
+                let value = Atom::<V>::new(self.entry_id(&key)).observe();
+                (key, value)
+            })
+            .collect()
+    }
+
+    /// Inserts or updates the value for `key`, returning the previous one.
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        let previous = self.get(&key);
+        let entry_id = self.entry_id(&key);
+        batch(|| {
+            if previous.is_some() {
+                Atom::<V>::new(entry_id).set(value.clone());
+                record_diff(self.id, MapDiff::Update { key, value });
+            } else {
+                init_atom(entry_id, value.clone());
+                self.keys_atom().update(|keys| keys.push(key.clone()));
+                record_diff(self.id, MapDiff::Insert { key, value });
+            }
+        });
+        previous
+    }
+
+    /// Updates the value for `key` in place, if there is one.
+    pub fn update<F: FnOnce(&mut V)>(&self, key: &K, func: F) {
+        if let Some(mut value) = self.get(key) {
+            func(&mut value);
+            self.insert(key.clone(), value);
+        }
+    }
+
+    pub fn remove(&self, key: &K) -> Option<V> {
+        let value = self.get(key)?;
+        let entry_id = self.entry_id(key);
+        batch(|| {
+            self.keys_atom().update(|keys| keys.retain(|k| k != key));
+            with_store(|store_refcell| store_refcell.borrow_mut().remove_id(&entry_id));
+            record_diff(self.id, MapDiff::<K, V>::Remove { key: key.clone() });
+        });
+        Some(value)
+    }
+
+    pub fn clear(&self) {
+        batch(|| {
+            for key in self.keys() {
+                let entry_id = self.entry_id(&key);
+                with_store(|store_refcell| store_refcell.borrow_mut().remove_id(&entry_id));
+            }
+            self.keys_atom().set(vec![]);
+            record_diff(self.id, MapDiff::<K, V>::Clear);
+        })
+    }
+
+    /// Removes the map and the atoms of its entries from the store.
+    pub fn delete(self) {
+        let entry_ids = self.keys().iter().map(|key| self.entry_id(key)).collect();
+        delete_collection(self.id, entry_ids);
+    }
+
+    /// Calls `func` with every change made after subscribing, in order, once
+    /// propagation has completed, until the returned [`Subscription`] is
+    /// dropped.
+    #[track_caller]
+    pub fn on_diff<F: Fn(&MapDiff<K, V>) + 'static>(&self, func: F) -> Subscription {
+        subscribe_diffs(self.id, func)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{reactive_state_access::reaction::Reaction, *};
+
+    thread_local! {
+        static FIRST_ITEM_RUNS: Cell<u32> = const { Cell::new(0) };
+        static COUNT_RUNS: Cell<u32> = const { Cell::new(0) };
+    }
+
+    fn playlist() -> ReactiveVec<&'static str> {
+        ReactiveVec::at_call_site()
+    }
+
+    #[reaction]
+    fn first_track() -> Reaction<Option<&'static str>> {
+        FIRST_ITEM_RUNS.with(|runs| runs.set(runs.get() + 1));
+        playlist()
+            .observe_items()
+            .first()
+            .map(|item| item.observe())
+    }
+
+    #[reaction]
+    fn track_count() -> Reaction<usize> {
+        COUNT_RUNS.with(|runs| runs.set(runs.get() + 1));
+        playlist().observe_items().len()
+    }
+
+    #[test]
+    fn test_reactive_vec_item_level_changes() {
+        playlist().push("intro");
+        playlist().push("verse");
+        let (first, count) = (first_track(), track_count());
+        assert_eq!((first.get(), count.get()), (Some("intro"), 2));
+
+        playlist().set(1, "chorus");
+        assert_eq!(FIRST_ITEM_RUNS.with(Cell::get), 1, "item 0 did not change");
+        assert_eq!(COUNT_RUNS.with(Cell::get), 1, "no item was added");
+
+        playlist().set(0, "overture");
+        assert_eq!(first.get(), Some("overture"));
+        assert_eq!(COUNT_RUNS.with(Cell::get), 1);
+
+        let chorus = playlist().item(1).unwrap();
+        playlist().move_item(1, 0);
+        assert_eq!(
+            playlist().item(0).unwrap().id,
+            chorus.id,
+            "moves keep the atom"
+        );
+        assert_eq!(playlist().to_vec(), vec!["chorus", "overture"]);
+        assert_eq!(playlist().remove(1), "overture");
+        assert_eq!(count.get(), 1);
+    }
+
+    #[test]
+    fn test_reactive_vec_diffs() {
+        let steps = ReactiveVec::<u32>::new();
+        let diffs = Rc::new(RefCell::new(vec![]));
+        let seen = diffs.clone();
+        let subscription = steps.on_diff(move |diff| seen.borrow_mut().push(diff.clone()));
+
+        batch(|| {
+            steps.push(1);
+            steps.push(2);
+            steps.move_item(0, 1);
+        });
+        steps.update(0, |step| *step *= 10);
+        steps.remove(1);
+        steps.clear();
+        drop(subscription);
+        steps.push(3);
+
+        assert_eq!(
+            *diffs.borrow(),
+            vec![
+                VecDiff::Insert { index: 0, value: 1 },
+                VecDiff::Insert { index: 1, value: 2 },
+                VecDiff::Move { from: 0, to: 1 },
+                VecDiff::Update {
+                    index: 0,
+                    value: 20
+                },
+                VecDiff::Remove { index: 1 },
+                VecDiff::Clear,
+            ]
+        );
+        assert!(
+            journal::<VecDiff<u32>>(steps.id).borrow().diffs.is_empty(),
+            "seen diffs are dropped"
+        );
+    }
+
+    #[test]
+    fn test_reactive_map() {
+        let users = ReactiveMap::<u32, String>::new();
+        let diffs = Rc::new(RefCell::new(vec![]));
+        let seen = diffs.clone();
+        let _subscription = users.on_diff(move |diff| seen.borrow_mut().push(diff.clone()));
+
+        users.insert(1, "ada".to_string());
+        users.insert(2, "grace".to_string());
+        assert_eq!(users.insert(1, "Ada".to_string()), Some("ada".to_string()));
+        assert_eq!(users.keys(), vec![1, 2]);
+        assert_eq!(users.remove(&2), Some("grace".to_string()));
+        assert_eq!(users.remove(&2), None);
+        assert!(users.contains_key(&1));
+        assert_eq!(users.entry(&1).unwrap().get(), "Ada");
+
+        assert_eq!(
+            *diffs.borrow(),
+            vec![
+                MapDiff::Insert {
+                    key: 1,
+                    value: "ada".to_string()
+                },
+                MapDiff::Insert {
+                    key: 2,
+                    value: "grace".to_string()
+                },
+                MapDiff::Update {
+                    key: 1,
+                    value: "Ada".to_string()
+                },
+                MapDiff::Remove { key: 2 },
+            ]
+        );
+    }
+
+    #[derive(Default)]
+    struct Board {
+        columns: ReactiveMap<u32, String>,
+    }
+
+    #[test]
+    fn test_new_collections_are_distinct() {
+        let lists = (0..2)
+            .map(|_| ReactiveVec::<u32>::new())
+            .collect::<Vec<_>>();
+        lists[0].push(1);
+        assert!(lists[1].is_empty());
+
+        let (first, second) = (Board::default(), Board::default());
+        first.columns.insert(1, "todo".to_string());
+        assert!(second.columns.is_empty());
+        assert_eq!(playlist().id, playlist().id);
+    }
+
+    #[test]
+    fn test_collections_are_kept_until_deleted() {
+        let tracks = ReactiveVec::<u32>::at_call_site();
+        tracks.push(1);
+        reset_unseen_reactive_id_list();
+        assert!(purge_unseen_reactive_ids().is_empty());
+        assert_eq!(tracks.to_vec(), vec![1]);
+
+        let item = tracks.item(0).unwrap();
+        tracks.delete();
+        assert!(!item.state_exists());
+        assert!(!with_store(|store_refcell| store_refcell
+            .borrow()
+            .id_to_key_map
+            .contains_key(&tracks.id)));
+    }
+}
@@ -0,0 +1,293 @@
+use crate::{
+    reactive_state_functions::with_store,
+    store::{NodeKind, StorageKey},
+};
+use std::fmt::Write;
+
+/// An atom, reaction, effect or topological state in the reactive graph.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GraphNode {
+    pub id: StorageKey,
+    pub name: Option<&'static str>,
+    pub kind: NodeKind,
+}
+
+impl GraphNode {
+    /// The function name the node was declared with, or its key if unknown.
+    pub fn label(&self) -> String {
+        match self.name {
+            Some(name) => name.to_string(),
+            None => format!("{:?}", self.id),
+        }
+    }
+}
+
+/// A snapshot of the reactive dependency graph held in the store.
+///
+/// Every edge goes from a source to a reaction that observes it.
+///
+/// ```
+/// use atomic_hooks::{atom::Atom, reaction::Reaction, *};
+/// #[atom]
+/// fn a() -> Atom<i32> {
+///     0
+/// }
+/// #[reaction]
+/// fn a_doubled() -> Reaction<i32> {
+///     a().observe() * 2
+/// }
+///
+/// a_doubled();
+/// let graph = dependency_graph();
+/// assert_eq!(graph.dependents_of(a().id), vec![a_doubled().id]);
+/// println!("{}", graph.to_dot());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DependencyGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<(StorageKey, StorageKey)>,
+}
+
+/// Returns a snapshot of the current reactive dependency graph.
+pub fn dependency_graph() -> DependencyGraph {
+    with_store(|store_refcell| {
+        let store = store_refcell.borrow();
+        let edges = store.dependency_edges();
+
+        // topological state is only of interest when something observes it
+        let mut nodes = store
+            .id_to_key_map
+            .keys()
+            .filter(|id| match id {
+                StorageKey::SlottedKey(_) => true,
+                StorageKey::TopoKey(_) => edges.iter().any(|(source, _)| source == *id),
+            })
+            .filter_map(|id| {
+                Some(GraphNode {
+                    id: *id,
+                    name: store.name_of(id),
+                    kind: store.kind_of(id)?,
+                })
+            })
+            .collect::<Vec<_>>();
+        nodes.sort_by_key(|node| node.label());
+
+        DependencyGraph { nodes, edges }
+    })
+}
+
+impl DependencyGraph {
+    pub fn atoms(&self) -> impl Iterator<Item = &GraphNode> {
+        self.nodes.iter().filter(|node| node.kind == NodeKind::Atom)
+    }
+
+    pub fn reactions(&self) -> impl Iterator<Item = &GraphNode> {
+        self.nodes
+            .iter()
+            .filter(|node| node.kind == NodeKind::Reaction)
+    }
+
+    pub fn node(&self, id: StorageKey) -> Option<&GraphNode> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    /// The ids that directly observe `id`.
+    pub fn dependents_of(&self, id: StorageKey) -> Vec<StorageKey> {
+        self.edges
+            .iter()
+            .filter(|(source, _)| *source == id)
+            .map(|(_, reaction)| *reaction)
+            .collect()
+    }
+
+    /// The ids that `id` directly observes.
+    pub fn dependencies_of(&self, id: StorageKey) -> Vec<StorageKey> {
+        self.edges
+            .iter()
+            .filter(|(_, reaction)| *reaction == id)
+            .map(|(source, _)| *source)
+            .collect()
+    }
+
+    fn index_of(&self, id: StorageKey) -> Option<usize> {
+        self.nodes.iter().position(|node| node.id == id)
+    }
+
+    /// Renders the graph in Graphviz DOT format.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph reactive_graph {\n");
+        for (index, node) in self.nodes.iter().enumerate() {
+            let shape = match node.kind {
+                NodeKind::Atom => "box",
+                NodeKind::Reaction => "ellipse",
+                NodeKind::Effect => "diamond",
+                NodeKind::State => "note",
+            };
+            writeln!(
+                dot,
+                "    n{} [label=\"{}\", shape={}];",
+                index,
+                escape(&node.label()),
+                shape
+            )
+            .unwrap();
+        }
+        for (source, reaction) in &self.edges {
+            if let (Some(from), Some(to)) = (self.index_of(*source), self.index_of(*reaction)) {
+                writeln!(dot, "    n{} -> n{};", from, to).unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Renders the graph as JSON, edges refer to nodes by their index.
+    pub fn to_json(&self) -> String {
+        let nodes = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| {
+                let kind = match node.kind {
+                    NodeKind::Atom => "atom",
+                    NodeKind::Reaction => "reaction",
+                    NodeKind::Effect => "effect",
+                    NodeKind::State => "state",
+                };
+                serde_json::json!({
+                    "index": index,
+                    "key": format!("{:?}", node.id),
+                    "name": node.name,
+                    "kind": kind,
+                })
+            })
+            .collect::<Vec<_>>();
+        let edges = self
+            .edges
+            .iter()
+            .filter_map(|(source, reaction)| {
+                Some(serde_json::json!({
+                    "from": self.index_of(*source)?,
+                    "to": self.index_of(*reaction)?,
+                }))
+            })
+            .collect::<Vec<_>>();
+
+        serde_json::json!({ "nodes": nodes, "edges": edges }).to_string()
+    }
+}
+
+// escapes a string for use inside double quotes in DOT
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(escaped, "\\u{:04x}", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        reactive_state_access::{atom::Atom, reaction::Reaction},
+        *,
+    };
+
+    #[atom]
+    fn first_name() -> Atom<String> {
+        "Ada".to_string()
+    }
+
+    #[atom]
+    fn last_name() -> Atom<String> {
+        "Lovelace".to_string()
+    }
+
+    #[reaction]
+    fn full_name() -> Reaction<String> {
+        format!("{} {}", first_name().observe(), last_name().observe())
+    }
+
+    #[reaction]
+    fn greeting() -> Reaction<String> {
+        format!("Hello \"{}\"", full_name().observe())
+    }
+
+    #[test]
+    fn test_dependency_graph() {
+        greeting();
+        let graph = dependency_graph();
+
+        let atoms = graph
+            .atoms()
+            .filter_map(|node| node.name)
+            .filter(|name| name.starts_with("atomic_hooks::graph::"))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            atoms,
+            vec![
+                "atomic_hooks::graph::test::first_name",
+                "atomic_hooks::graph::test::last_name"
+            ]
+        );
+        assert_eq!(graph.reactions().count(), 2);
+        assert_eq!(graph.dependents_of(first_name().id), vec![full_name().id]);
+        assert_eq!(
+            graph.dependencies_of(full_name().id),
+            vec![first_name().id, last_name().id]
+        );
+        assert_eq!(graph.dependents_of(full_name().id), vec![greeting().id]);
+    }
+
+    #[test]
+    fn test_dot_export() {
+        greeting();
+        let dot = dependency_graph().to_dot();
+
+        assert!(dot.starts_with("digraph reactive_graph {\n"));
+        assert!(dot.contains("[label=\"atomic_hooks::graph::test::first_name\", shape=box];"));
+        assert!(dot.contains("[label=\"atomic_hooks::graph::test::full_name\", shape=ellipse];"));
+        assert_eq!(dot.matches(" -> ").count(), 3);
+    }
+
+    #[test]
+    fn test_json_export() {
+        greeting();
+        let json = dependency_graph().to_json();
+        let json = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+
+        assert_eq!(json["nodes"][0]["index"], 0);
+        assert!(json["nodes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|node| node["name"] == "atomic_hooks::graph::test::greeting"
+                && node["kind"] == "reaction"));
+        assert_eq!(json["edges"].as_array().unwrap().len(), 3);
+        assert_eq!(escape("say \"hi\"\n"), "say \\\"hi\\\"\\n");
+    }
+
+    #[test]
+    fn test_json_export_escapes_control_characters() {
+        let graph = DependencyGraph {
+            nodes: vec![GraphNode {
+                id: greeting().id,
+                name: Some("bell\u{7}\u{1b}[0m"),
+                kind: NodeKind::Reaction,
+            }],
+            edges: vec![],
+        };
+        let json = serde_json::from_str::<serde_json::Value>(&graph.to_json()).unwrap();
+
+        assert_eq!(json["nodes"][0]["name"], "bell\u{7}\u{1b}[0m");
+    }
+}
@@ -1,6 +1,7 @@
 use crate::reactive_state_access::state_access::{CloneState, StateAccess};
 use crate::{
-    reactive_state_functions::{execute_reaction_nodes, STORE},
+    middleware::intercept_update,
+    reactive_state_functions::{execute_reaction_nodes, with_store},
     store::{ReactiveContext, SlottedKey, StorageKey, TopoKey},
     unmount::Unmount,
 };
@@ -65,7 +66,7 @@ pub fn new_state<T: 'static, F: FnOnce() -> T>(data_fn: F) -> StateAccess<T> {
 
 /// Sets the state of type T keyed to the given TopoId
 pub fn set_state_with_topo_id<T: 'static>(data: T, current_id: TopoKey) {
-    STORE.with(|store_refcell| {
+    with_store(|store_refcell| {
         store_refcell
             .borrow_mut()
             .set_state_with_id::<T>(data, &StorageKey::TopoKey(current_id))
@@ -75,7 +76,7 @@ pub fn set_state_with_topo_id<T: 'static>(data: T, current_id: TopoKey) {
 }
 
 pub fn state_exists_for_topo_id<T: 'static>(id: TopoKey) -> bool {
-    STORE.with(|store_refcell| {
+    with_store(|store_refcell| {
         store_refcell
             .borrow()
             .state_exists_with_id::<T>(StorageKey::TopoKey(id))
@@ -97,7 +98,7 @@ fn get_state_slotted_key_struct_if_in_context() -> Option<SlottedKey> {
 
 /// Clones the state of type T keyed to the given TopoId
 pub fn clone_state_with_topo_id<T: 'static + Clone>(id: TopoKey) -> Option<T> {
-    STORE.with(|store_refcell| {
+    with_store(|store_refcell| {
         store_refcell
             .borrow_mut()
             .get_state_with_id::<T>(&StorageKey::TopoKey(id))
@@ -106,7 +107,7 @@ pub fn clone_state_with_topo_id<T: 'static + Clone>(id: TopoKey) -> Option<T> {
 }
 
 pub fn remove_state_with_topo_id<T: 'static>(id: TopoKey) -> Option<T> {
-    STORE.with(|store_refcell| {
+    with_store(|store_refcell| {
         store_refcell
             .borrow_mut()
             .remove_state_with_id::<T>(&StorageKey::TopoKey(id))
@@ -122,12 +123,10 @@ pub fn remove_state_with_topo_id<T: 'static>(id: TopoKey) -> Option<T> {
 ///     v.push("foo".to_string()
 /// )
 pub fn update_state_with_topo_id<T: 'static, F: FnOnce(&mut T) -> ()>(id: TopoKey, func: F) {
-    let mut item = remove_state_with_topo_id::<T>(id)
-        .expect("You are trying to update a type state that doesnt exist in this context!");
-    func(&mut item);
-    set_state_with_topo_id(item, id);
-
-    execute_reaction_nodes(&StorageKey::TopoKey(id));
+    // a vetoed update leaves the state as it was, without re-running anything
+    if intercept_update(StorageKey::TopoKey(id), func) {
+        execute_reaction_nodes(&StorageKey::TopoKey(id));
+    }
 }
 
 pub fn read_state_with_topo_id<T: 'static, F: FnOnce(&T) -> R, R>(id: TopoKey, func: F) -> R {
@@ -156,7 +155,7 @@ pub fn read_state_with_topo_id<T: 'static, F: FnOnce(&T) -> R, R>(id: TopoKey, f
 /// accessed
 
 pub fn reset_unseen_id_list() {
-    STORE.with(|store_refcell| {
+    with_store(|store_refcell| {
         let mut store_mut = store_refcell.borrow_mut();
 
         store_mut.unseen_ids = HashSet::new();
@@ -177,7 +176,7 @@ pub fn reset_unseen_id_list() {
 }
 
 pub fn unseen_ids() -> Vec<TopoKey> {
-    STORE.with(|store_refcell| {
+    with_store(|store_refcell| {
         let store_mut = store_refcell.borrow_mut();
         store_mut.unseen_ids.iter().cloned().collect::<Vec<_>>()
     })
@@ -1,4 +1,4 @@
-pub use atomic_hooks_macros::{atom, reaction};
+pub use atomic_hooks_macros::{atom, reaction, selector};
 // storage
 pub mod store;
 
@@ -11,6 +11,26 @@ mod marker;
 mod reactive_state_access;
 pub mod reactive_state_functions;
 
+// introspection
+pub mod graph;
+pub mod snapshot;
+
+// persistence
+pub mod persist;
+pub mod ssr;
+
+// async
+pub mod resource;
+
+// transactions
+pub mod transaction;
+
+// middleware
+pub mod middleware;
+
+// collections
+pub mod collections;
+
 // helpers
 mod helpers;
 // mod seed_integration;
@@ -0,0 +1,597 @@
+use crate::{
+    reactive_state_functions::{
+        debug_name_of, reactive_state_exists_for_id, set_inert_atom_state_with_id, with_store,
+    },
+    reverse::Reversible,
+    store::StorageKey,
+};
+use std::{
+    any::{type_name, Any, TypeId},
+    collections::HashMap,
+    fmt::Debug,
+    marker::PhantomData,
+    rc::Rc,
+};
+
+/// A write to an atom, as seen by write middleware.
+///
+/// The old and new values are only formatted for types registered with
+/// [`register_debug_type`], which `#[atom]` does for every `Debug` type. The
+/// primitive types and `String` are always registered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AtomWrite {
+    pub id: StorageKey,
+    pub type_name: &'static str,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl AtomWrite {
+    /// The debug name of the atom written to.
+    pub fn debug_name(&self) -> String {
+        debug_name_of(self.id)
+    }
+}
+
+/// The handle of a middleware added with [`add_write_middleware`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MiddlewareId(u64);
+
+// sees every write, and returns whether it is allowed
+pub type WriteMiddleware = Rc<dyn Fn(&AtomWrite) -> bool>;
+// formats a value of a registered type
+pub type ValueFormatter = fn(&dyn Any) -> String;
+// clones a value of a registered type
+pub type ValueCloner = fn(&dyn Any) -> Box<dyn Any>;
+
+// sees the writes to a single atom, and may change the value written
+type InterceptorFn<T> = dyn Fn(&AtomWrite, &mut T) -> bool;
+struct Interceptor<T>(Rc<InterceptorFn<T>>);
+
+/// Calls `func` on every `set`, `update` and `patch` of an `Atom`,
+/// `ReversibleAtom` or `StateAccess`. Returning `false` vetoes the write, which leaves the atom as
+/// it was. Undo, redo and rolled back transactions put values back without
+/// calling it.
+///
+/// ```
+/// use atomic_hooks::{atom::Atom, *};
+/// #[atom]
+/// fn volume() -> Atom<u32> {
+///     5
+/// }
+///
+/// let log = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+/// let writes = log.clone();
+/// let middleware = add_write_middleware(move |write| {
+///     writes
+///         .borrow_mut()
+///         .push(format!("{:?} -> {:?}", write.old, write.new));
+///     write.new.as_deref() != Some("11")
+/// });
+///
+/// volume().set(7);
+/// volume().set(11);
+/// assert_eq!(volume().get(), 7);
+/// assert_eq!(log.borrow()[0], "Some(\"5\") -> Some(\"7\")");
+///
+/// remove_write_middleware(middleware);
+/// volume().set(11);
+/// assert_eq!(volume().get(), 11);
+/// ```
+pub fn add_write_middleware<F: Fn(&AtomWrite) -> bool + 'static>(func: F) -> MiddlewareId {
+    with_store(|store_refcell| {
+        let mut store = store_refcell.borrow_mut();
+        let id = store.next_middleware_id;
+        store.next_middleware_id += 1;
+        store.write_middleware.push((id, Rc::new(func)));
+        MiddlewareId(id)
+    })
+}
+
+/// Removes a middleware added with [`add_write_middleware`].
+pub fn remove_write_middleware(middleware: MiddlewareId) {
+    with_store(|store_refcell| {
+        store_refcell
+            .borrow_mut()
+            .write_middleware
+            .retain(|(id, _)| *id != middleware.0)
+    });
+}
+
+/// Calls `func` on every write to the state `id`, before any middleware
+/// added with [`add_write_middleware`]. It may change the value written, or
+/// return `false` to veto the write.
+///
+/// Typically this is used via `intercept` on an atom. The type is registered
+/// with [`register_clone_type`], so that updates can be intercepted on a
+/// clone of the value.
+pub fn intercept_writes_with_id<T, F>(id: StorageKey, func: F)
+where
+    T: Clone + 'static,
+    F: Fn(&AtomWrite, &mut T) -> bool + 'static,
+{
+    register_clone_type::<T>();
+    set_inert_atom_state_with_id(Interceptor::<T>(Rc::new(func)), id);
+}
+
+fn format_value<T: Debug + 'static>(value: &dyn Any) -> String {
+    format!(
+        "{:?}",
+        value
+            .downcast_ref::<T>()
+            .expect("value of the registered type")
+    )
+}
+
+fn clone_value<T: Clone + 'static>(value: &dyn Any) -> Box<dyn Any> {
+    Box::new(
+        value
+            .downcast_ref::<T>()
+            .expect("value of the registered type")
+            .clone(),
+    )
+}
+
+/// Formats the values of type `T` in the writes seen by middleware.
+pub fn register_debug_type<T: Debug + 'static>() {
+    with_store(|store_refcell| {
+        store_refcell
+            .borrow_mut()
+            .value_formatters
+            .entry(TypeId::of::<T>())
+            .or_insert(format_value::<T>);
+    });
+}
+
+/// Allows middleware to veto updates of values of type `T`.
+///
+/// `#[atom]` does this for every `Clone` type and the primitive types and
+/// `String` are always registered, other types stored with `use_state` need
+/// to be registered before their updates can be vetoed.
+pub fn register_clone_type<T: Clone + 'static>() {
+    with_store(|store_refcell| {
+        store_refcell
+            .borrow_mut()
+            .value_cloners
+            .entry(TypeId::of::<T>())
+            .or_insert(clone_value::<T>);
+    });
+}
+
+// `use_state` is generic over any type, so it cannot tell which of its types
+// are Debug or Clone, the common ones are registered for every store instead
+macro_rules! std_types {
+    ($($ty:ty),*) => {
+        pub(crate) fn std_value_formatters() -> HashMap<TypeId, ValueFormatter> {
+            let mut formatters = HashMap::new();
+            $(formatters.insert(TypeId::of::<$ty>(), format_value::<$ty> as ValueFormatter);)*
+            formatters
+        }
+
+        pub(crate) fn std_value_cloners() -> HashMap<TypeId, ValueCloner> {
+            let mut cloners = HashMap::new();
+            $(cloners.insert(TypeId::of::<$ty>(), clone_value::<$ty> as ValueCloner);)*
+            cloners
+        }
+    };
+}
+
+std_types!(
+    bool,
+    char,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    f32,
+    f64,
+    String,
+    &'static str
+);
+
+/// Registers the type of an atom with [`register_debug_type`], for use by
+/// `#[atom]`.
+///
+/// Types that are not `Debug` are skipped through the [`SkipDebug`]
+/// fallback.
+#[derive(Default)]
+pub struct DebugRegistrar<T> {
+    _phantom_data_stored_type: PhantomData<T>,
+}
+
+impl<T> DebugRegistrar<T> {
+    pub fn new() -> Self {
+        DebugRegistrar {
+            _phantom_data_stored_type: PhantomData,
+        }
+    }
+}
+
+pub trait RegisterDebug {
+    fn register_debug(&self);
+}
+
+impl<T: Debug + 'static> RegisterDebug for DebugRegistrar<T> {
+    fn register_debug(&self) {
+        register_debug_type::<T>()
+    }
+}
+
+pub trait SkipDebug {
+    fn register_debug(&self);
+}
+
+impl<T> SkipDebug for &DebugRegistrar<T> {
+    fn register_debug(&self) {}
+}
+
+// marks a state that write middleware does not see
+struct SkipMiddleware;
+
+/// Hides the writes to the state `id` from the middleware added with
+/// [`add_write_middleware`], as is done for the undo histories.
+pub fn skip_write_middleware_with_id(id: StorageKey) {
+    if !reactive_state_exists_for_id::<SkipMiddleware>(id) {
+        set_inert_atom_state_with_id(SkipMiddleware, id);
+    }
+}
+
+fn has_write_middleware<T: 'static>(id: StorageKey) -> bool {
+    with_store(|store_refcell| {
+        let store = store_refcell.borrow();
+        store.state_exists_with_id::<Interceptor<T>>(id)
+            || !store.write_middleware.is_empty()
+                && !store.state_exists_with_id::<SkipMiddleware>(id)
+    })
+}
+
+fn format_with<T: 'static>(value: &T) -> Option<String> {
+    let formatter = with_store(|store_refcell| {
+        store_refcell
+            .borrow()
+            .value_formatters
+            .get(&TypeId::of::<T>())
+            .copied()
+    });
+    formatter.map(|formatter| formatter(value))
+}
+
+// the current value of the state `id`, formatted if its type is registered
+fn format_state<T: 'static>(id: StorageKey) -> Option<String> {
+    with_store(|store_refcell| {
+        let store = store_refcell.borrow();
+        let formatter = store.value_formatters.get(&TypeId::of::<T>())?;
+        store
+            .get_state_with_id::<T>(&id)
+            .map(|value| formatter(value))
+    })
+}
+
+// a clone of the state `id`, if its type is registered
+fn clone_state<T: 'static>(id: StorageKey) -> Option<T> {
+    with_store(|store_refcell| {
+        let store = store_refcell.borrow();
+        let cloner = store.value_cloners.get(&TypeId::of::<T>())?;
+        let value = store.get_state_with_id::<T>(&id)?;
+        cloner(value).downcast::<T>().ok().map(|value| *value)
+    })
+}
+
+// calls `func` on the state `id` in place
+fn update_state<T: 'static, F: FnOnce(&mut T) -> R, R>(id: StorageKey, func: F) -> R {
+    let mut item =
+        with_store(|store_refcell| store_refcell.borrow_mut().remove_state_with_id::<T>(&id))
+            .expect("You are trying to update a type state that doesnt exist in this context!");
+    let result = func(&mut item);
+    with_store(|store_refcell| store_refcell.borrow_mut().set_state_with_id(item, &id));
+    result
+}
+
+fn middleware_of(id: StorageKey) -> Vec<WriteMiddleware> {
+    with_store(|store_refcell| {
+        let store = store_refcell.borrow();
+        if store.state_exists_with_id::<SkipMiddleware>(id) {
+            vec![]
+        } else {
+            store
+                .write_middleware
+                .iter()
+                .map(|(_, middleware)| middleware.clone())
+                .collect()
+        }
+    })
+}
+
+// runs the interceptor of `id` and then every middleware on a write of `new`
+fn allow_write<T: 'static>(id: StorageKey, old: Option<String>, new: &mut T) -> bool {
+    let interceptor = with_store(|store_refcell| {
+        store_refcell
+            .borrow()
+            .get_state_with_id::<Interceptor<T>>(&id)
+            .map(|interceptor| interceptor.0.clone())
+    });
+
+    let mut write = AtomWrite {
+        id,
+        type_name: type_name::<T>(),
+        old,
+        new: format_with(new),
+    };
+    if let Some(interceptor) = interceptor {
+        if !interceptor(&write, new) {
+            return false;
+        }
+        write.new = format_with(new);
+    }
+    middleware_of(id)
+        .iter()
+        .all(|middleware| middleware(&write))
+}
+
+// runs every middleware on a write that has already been made, as there is
+// no interceptor for types that are not registered with `register_clone_type`
+fn allow_written<T: 'static>(id: StorageKey, old: Option<String>) -> bool {
+    let write = AtomWrite {
+        id,
+        type_name: type_name::<T>(),
+        old,
+        new: format_state::<T>(id),
+    };
+    middleware_of(id)
+        .iter()
+        .all(|middleware| middleware(&write))
+}
+
+/// Passes a write of `new` to the state `id` through its interceptor and the
+/// write middleware, returning the value to write or `None` if it is vetoed.
+pub fn intercept_set<T: 'static>(id: StorageKey, mut new: T) -> Option<T> {
+    if !has_write_middleware::<T>(id) {
+        return Some(new);
+    }
+
+    let old = format_state::<T>(id);
+    if allow_write(id, old, &mut new) {
+        Some(new)
+    } else {
+        None
+    }
+}
+
+/// Updates the state `id` with `func`, passing the update through its
+/// interceptor and the write middleware. Returns `false` if the update is
+/// vetoed, in which case the state is left as it was.
+///
+/// The update is made to a clone of the value, so that interceptors and
+/// middleware can still read the state. Types that are not registered with
+/// [`register_clone_type`] are updated in place, a veto of such an update
+/// cannot be undone and is ignored with a warning.
+pub fn intercept_update<T: 'static, F: FnOnce(&mut T)>(id: StorageKey, func: F) -> bool {
+    if !has_write_middleware::<T>(id) {
+        update_state(id, func);
+        return true;
+    }
+
+    let old = format_state::<T>(id);
+    match clone_state::<T>(id) {
+        Some(mut item) => {
+            func(&mut item);
+            let allowed = allow_write(id, old, &mut item);
+            if allowed {
+                with_store(|store_refcell| store_refcell.borrow_mut().set_state_with_id(item, &id));
+            }
+            allowed
+        }
+        None => {
+            update_state(id, func);
+            if !allow_written::<T>(id, old) {
+                eprintln!(
+                    "an update of {} was vetoed, but the type is not registered with \
+                     `register_clone_type` so the update is kept",
+                    type_name::<T>()
+                );
+            }
+            true
+        }
+    }
+}
+
+/// Applies `patch` to the state `id` and passes the patched value through
+/// its interceptor and the write middleware. Returns the inverse of the
+/// patch, or `None` if the write is vetoed, in which case the state is left
+/// as it was.
+///
+/// Changes an interceptor makes to the patched value are kept, but are not
+/// part of the patch recorded for undo.
+pub fn intercept_patch<T: Reversible + 'static>(
+    id: StorageKey,
+    patch: T::Patch,
+) -> Option<T::Patch> {
+    if !has_write_middleware::<T>(id) {
+        return Some(update_state(id, |item: &mut T| item.apply_patch(patch)));
+    }
+
+    let old = format_state::<T>(id);
+    match clone_state::<T>(id) {
+        Some(mut item) => {
+            let inverse = item.apply_patch(patch);
+            if !allow_write(id, old, &mut item) {
+                return None;
+            }
+            with_store(|store_refcell| store_refcell.borrow_mut().set_state_with_id(item, &id));
+            Some(inverse)
+        }
+        None => {
+            let inverse = update_state(id, |item: &mut T| item.apply_patch(patch));
+            if allow_written::<T>(id, old) {
+                Some(inverse)
+            } else {
+                update_state(id, |item: &mut T| item.apply_patch(inverse));
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        reactive_state_access::{
+            atom::Atom, reversible_atom::ReversibleAtom, state_access::CloneState,
+        },
+        *,
+    };
+
+    #[atom]
+    fn username() -> Atom<String> {
+        "ada".to_string()
+    }
+
+    #[atom(reversible)]
+    fn stars() -> ReversibleAtom<u32> {
+        0
+    }
+
+    #[derive(Clone, Debug, Default)]
+    struct Likes(u32);
+
+    impl Reversible for Likes {
+        type Patch = i64;
+
+        fn apply_patch(&mut self, by: i64) -> i64 {
+            self.0 = (i64::from(self.0) + by) as u32;
+            -by
+        }
+    }
+
+    #[atom(reversible)]
+    fn likes() -> ReversibleAtom<Likes> {
+        Likes::default()
+    }
+
+    #[test]
+    fn test_interceptor_transforms_and_vetoes() {
+        username().intercept(|_, name: &mut String| {
+            *name = name.trim().to_lowercase();
+            !name.is_empty()
+        });
+
+        username().set("  Grace ".to_string());
+        assert_eq!(username().get(), "grace");
+        username().update(|name| name.clear());
+        assert_eq!(username().get(), "grace", "the update is put back");
+        username().set(" ".to_string());
+        assert_eq!(username().get(), "grace");
+    }
+
+    #[test]
+    fn test_middleware_sees_every_write() {
+        let writes = Rc::new(std::cell::RefCell::new(vec![]));
+        let seen = writes.clone();
+        let middleware = add_write_middleware(move |write| {
+            seen.borrow_mut().push(write.clone());
+            true
+        });
+
+        stars().update(|stars| *stars += 1);
+        global_reverse_queue().travel_backwards();
+        assert_eq!(stars().get(), 0);
+        let state = use_state(|| 1u8);
+        state.set(2);
+        remove_write_middleware(middleware);
+        stars().set(3);
+
+        let writes = writes.borrow();
+        assert_eq!(writes.len(), 2, "undo is not a write");
+        assert_eq!(writes[0].type_name, "u32");
+        assert_eq!(writes[0].debug_name(), stars().debug_name());
+        assert_eq!(
+            (writes[0].old.as_deref(), writes[0].new.as_deref()),
+            (Some("0"), Some("1"))
+        );
+        assert_eq!(writes[1].id, StorageKey::TopoKey(state.id));
+        assert_eq!(
+            writes[1].new.as_deref(),
+            Some("2"),
+            "u8 is always registered"
+        );
+    }
+
+    #[test]
+    fn test_vetoed_reversible_write_is_not_recorded() {
+        stars().intercept(|_, stars: &mut u32| *stars <= 5);
+        stars().set(3);
+        stars().set(6);
+        stars().update(|stars| *stars += 10);
+        assert_eq!(stars().get(), 3);
+        assert_eq!(global_reverse_queue().len(), 1);
+    }
+
+    #[test]
+    fn test_vetoed_state_update_is_put_back() {
+        let state = use_state(|| 1);
+        let runs = Rc::new(std::cell::Cell::new(0));
+        let effect_runs = runs.clone();
+        let _subscription = state.subscribe(move |_, _| effect_runs.set(effect_runs.get() + 1));
+        let middleware = add_write_middleware(|write| write.new.as_deref() != Some("2"));
+
+        state.update(|state| *state += 1);
+        assert_eq!(state.get(), 1);
+        assert_eq!(runs.get(), 0, "a vetoed update does not re-run dependents");
+        state.update(|state| *state += 2);
+        assert_eq!(state.get(), 3);
+        assert!(runs.get() > 0);
+        remove_write_middleware(middleware);
+    }
+
+    #[test]
+    fn test_vetoed_patch_is_reverted() {
+        likes().intercept(|_, likes: &mut Likes| likes.0 <= 5);
+        likes().patch(3);
+        likes().patch(4);
+        assert_eq!(likes().get().0, 3);
+        assert_eq!(global_reverse_queue().len(), 1);
+
+        global_reverse_queue().travel_backwards();
+        assert_eq!(likes().get().0, 0);
+    }
+
+    // neither Clone nor registered, so its updates cannot be vetoed
+    struct Draft(u32);
+
+    #[test]
+    fn test_veto_of_unregistered_update_is_ignored() {
+        let draft = use_state(|| Draft(1));
+        let middleware = add_write_middleware(|_| false);
+        draft.update(|draft| draft.0 = 2);
+        remove_write_middleware(middleware);
+        assert_eq!(draft.get_with(|draft| draft.0), 2);
+    }
+
+    #[test]
+    fn test_middleware_reads_the_atom_being_written() {
+        let seen = Rc::new(std::cell::RefCell::new(vec![]));
+        let read = seen.clone();
+        let middleware = add_write_middleware(move |_| {
+            read.borrow_mut().push((username().get(), stars().get()));
+            true
+        });
+        username().update(|name| name.push('!'));
+        stars().update(|stars| *stars += 1);
+        remove_write_middleware(middleware);
+
+        assert_eq!(
+            *seen.borrow(),
+            vec![("ada".to_string(), 0), ("ada!".to_string(), 0)]
+        );
+        assert_eq!(stars().get(), 1);
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{
+    reactive_state_access::{atom::Atom, effect::effect, observable::Observable},
+    reactive_state_functions::{
+        reactive_state_exists_for_id, set_inert_atom_state_with_id, with_store,
+    },
+    store::StorageKey,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+/// Where `#[atom(persist = "key")]` atoms are read from and written to.
+///
+/// Values are stored as JSON strings.
+pub trait StorageBackend {
+    fn get_item(&self, key: &str) -> Option<String>;
+    fn set_item(&self, key: &str, value: &str);
+    fn remove_item(&self, key: &str);
+}
+
+/// An in-memory storage backend, the default for every store.
+///
+/// Clones share the same items, so a clone can be kept to inspect what was
+/// persisted.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryBackend {
+    items: Rc<RefCell<HashMap<String, String>>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> MemoryBackend {
+        MemoryBackend::default()
+    }
+}
+
+impl StorageBackend for MemoryBackend {
+    fn get_item(&self, key: &str) -> Option<String> {
+        self.items.borrow().get(key).cloned()
+    }
+
+    fn set_item(&self, key: &str, value: &str) {
+        self.items
+            .borrow_mut()
+            .insert(key.to_string(), value.to_string());
+    }
+
+    fn remove_item(&self, key: &str) {
+        self.items.borrow_mut().remove(key);
+    }
+}
+
+/// Sets the backend persisted atoms in the current store use. Atoms that
+/// already exist are not re-hydrated.
+pub fn set_storage_backend<B: StorageBackend + 'static>(backend: B) {
+    with_store(|store_refcell| store_refcell.borrow_mut().storage_backend = Rc::new(backend));
+}
+
+fn storage_backend() -> Rc<dyn StorageBackend> {
+    with_store(|store_refcell| store_refcell.borrow().storage_backend.clone())
+}
+
+// marks an atom whose changes are already written to the backend
+struct Persisted;
+
+/// Returns the value stored under `key` when the atom for `id` is first
+/// created, or `None` if there is none or it cannot be deserialized.
+///
+/// Typically this is used via `#[atom(persist = "key")]`.
+pub fn take_persisted_value<T: 'static + DeserializeOwned>(id: StorageKey, key: &str) -> Option<T> {
+    if reactive_state_exists_for_id::<T>(id) {
+        return None;
+    }
+    serde_json::from_str(&storage_backend().get_item(key)?).ok()
+}
+
+/// Writes the atom for `id` to the storage backend under `key` every time it
+/// changes.
+///
+/// Typically this is used via `#[atom(persist = "key")]`.
+pub fn persist_atom_with_id<T: 'static + Clone + Serialize>(id: StorageKey, key: &'static str) {
+    if reactive_state_exists_for_id::<Persisted>(id) {
+        return;
+    }
+    set_inert_atom_state_with_id(Persisted, id);
+
+    let atom = Atom::<T>::new(id);
+    effect(move || {
+        let value = atom.observe();
+        if let Ok(json) = serde_json::to_string(&value) {
+            storage_backend().set_item(key, &json);
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{reactive_state_access::atom::Atom, *};
+
+    #[atom(persist = "volume")]
+    fn volume() -> Atom<u8> {
+        50
+    }
+
+    #[atom(persist = "recent_searches")]
+    fn recent_searches() -> Atom<Vec<String>> {
+        vec![]
+    }
+
+    #[test]
+    fn test_persisted_atom_is_hydrated() {
+        let backend = MemoryBackend::new();
+        backend.set_item("recent_searches", "[\"rust\",\"seed\"]");
+        set_storage_backend(backend.clone());
+
+        assert_eq!(recent_searches().get(), vec!["rust", "seed"]);
+        recent_searches().update(|searches| searches.push("atoms".to_string()));
+        assert_eq!(
+            backend.get_item("recent_searches").unwrap(),
+            "[\"rust\",\"seed\",\"atoms\"]"
+        );
+
+        recent_searches().reset_to_default();
+        assert_eq!(recent_searches().get(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_persisted_atom_is_written_back() {
+        let backend = MemoryBackend::new();
+        backend.set_item("volume", "not json");
+        set_storage_backend(backend.clone());
+
+        assert_eq!(
+            volume().get(),
+            50,
+            "invalid values fall back to the default"
+        );
+        volume().set(80);
+        assert_eq!(backend.get_item("volume").unwrap(), "80");
+    }
+}
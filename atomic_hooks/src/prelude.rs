@@ -31,18 +31,48 @@
 /// retrieved
 pub use crate::marker::*;
 pub use crate::{
+    collections::{MapDiff, ReactiveMap, ReactiveVec, VecDiff},
+    graph::{dependency_graph, DependencyGraph, GraphNode},
+    middleware::{
+        add_write_middleware, intercept_patch, intercept_set, intercept_update,
+        intercept_writes_with_id, register_clone_type, register_debug_type,
+        remove_write_middleware, skip_write_middleware_with_id, AtomWrite, DebugRegistrar,
+        MiddlewareId, RegisterDebug, SkipDebug, ValueCloner, ValueFormatter, WriteMiddleware,
+    },
+    persist::{
+        persist_atom_with_id, set_storage_backend, take_persisted_value, MemoryBackend,
+        StorageBackend,
+    },
     reactive_state_access::{atom, reaction, reversible_atom},
     reactive_state_functions::{
-        atom, atom_reverse, clone_reactive_state_with_id, reaction, reaction_start_suspended,
-        reactive_state_exists_for_id, read_reactive_state_with_id, remove_reactive_state_with_id,
-        return_key_for_type_and_insert_if_required, set_inert_atom_reversible_state_with_id,
+        atom, atom_reverse, batch, clone_reactive_state_with_id, debug_name_of, id_by_debug_name,
+        keep_reactive_state_alive_with_id, memoize_reactive_state_with_id,
+        name_reactive_state_with_id, purge_unseen_reactive_ids, reaction, reaction_lazy,
+        reaction_start_suspended, reactive_state_exists_for_id, read_reactive_state_with_id,
+        remove_reactive_state_with_id, reset_unseen_reactive_id_list,
+        return_key_for_name_and_insert_if_required, return_key_for_type_and_insert_if_required,
+        run_in_reactive_context, set_inert_atom_reversible_state_with_id,
         set_inert_atom_state_with_id, try_read_reactive_state_with_id, unlink_dead_links,
-        update_atom_state_with_id, UndoVec,
+        unseen_reactive_ids, update_atom_state_with_id, with_store, UndoVec,
+    },
+    resource::{
+        resource, resource_with_id, set_spawner, Loadable, LocalExecutor, LocalFuture, Spawner,
+    },
+    reverse::{
+        global_reverse_queue, record_undo_command, record_undo_command_in, set_undo_scope,
+        undo_history_of, undo_scope, undoable, GlobalUndo, HistoryEntry, Reversible,
+    },
+    snapshot::{restore, snapshot, RegisterSnapshot, SkipSnapshot, Snapshot, SnapshotRegistrar},
+    ssr::{
+        dehydrate, hydrate, stable_hash, HydrateRegistrar, RegisterHydrate, SkipHydrate,
+        StableHasher,
+    },
+    store::{
+        DependencyCycle, Memo, NodeKind, ReactiveContext, RxFunc, StateName, StoreHandle, TopoKey,
     },
-    reverse::{global_reverse_queue, GlobalUndo},
-    store::{ReactiveContext, RxFunc, TopoKey},
+    transaction::{journal_write, transaction},
 };
-pub use atomic_hooks_macros::{atom, reaction};
+pub use atomic_hooks_macros::{atom, reaction, selector};
 // pub use crate::local_update_el::{LocalUpdateEl2,Local,};
 pub use illicit;
 pub use topo;
@@ -58,5 +88,9 @@ pub use crate::{
     unmount::{StateAccessUnmount, Unmount},
 };
 
+pub use crate::reactive_state_access::effect::{effect, effect_with_cleanup, Effect, Subscription};
+pub use crate::reactive_state_access::family::AtomFamily;
+pub use crate::reactive_state_access::lens::Lens;
 pub use crate::reactive_state_access::observable::Observable;
+pub use crate::reactive_state_access::selector::{register_selector_setter, Selector};
 pub use crate::reactive_state_access::*;
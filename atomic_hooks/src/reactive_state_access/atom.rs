@@ -35,6 +35,33 @@ where
         }
     }
 
+    /// The function path the atom was declared with, followed by the hash of
+    /// its arguments if it has any.
+    ///
+    /// ```
+    /// use atomic_hooks::{atom::Atom, *};
+    /// #[atom]
+    /// fn user_name(id: u32) -> Atom<String> {
+    ///     format!("user {}", id)
+    /// }
+    ///
+    /// let name = user_name(1).debug_name();
+    /// assert!(name.starts_with("rust_out::user_name#"));
+    /// assert_eq!(
+    ///     Atom::<String>::by_name(&name).map(|atom| atom.id),
+    ///     Some(user_name(1).id)
+    /// );
+    /// ```
+    pub fn debug_name(&self) -> String {
+        debug_name_of(self.id)
+    }
+
+    /// Finds the atom with the given [`debug_name`](Atom::debug_name), if
+    /// it has been created and holds a `T`.
+    pub fn by_name(debug_name: &str) -> Option<Atom<T>> {
+        find_by_debug_name::<T>(debug_name, NodeKind::Atom).map(Atom::new)
+    }
+
     /// Stores a value of type T in a backing Store **without** reaction for
     /// observers.
     ///
@@ -68,6 +95,7 @@ where
     where
         T: 'static,
     {
+        journal_write(self.id);
         set_inert_atom_state_with_id(value, self.id);
     }
     /// Stores a value of type T in a backing Store **with** a reaction for
@@ -117,6 +145,26 @@ where
         update_atom_state_with_id(self.id, func);
     }
 
+    /// Calls `func` on every write to the atom, which may change the value
+    /// written or veto it, see [`intercept_writes_with_id`].
+    pub fn intercept<F: Fn(&AtomWrite, &mut T) -> bool + 'static>(&self, func: F)
+    where
+        T: Clone,
+    {
+        intercept_writes_with_id(self.id, func);
+    }
+
+    /// A [`Lens`] onto a field of the atom, see [`Lens`] for an example.
+    /// Each call site creates a single lens per atom and `field` name.
+    #[track_caller]
+    pub fn map_field<F>(&self, field: &'static str, project: fn(&mut T) -> &mut F) -> Lens<T, F>
+    where
+        T: Clone,
+        F: 'static + Clone + PartialEq,
+    {
+        Lens::new(*self, field, project)
+    }
+
     /// Use to remove an atom from the global state
     /// ```
     /// use atomic_hooks::atom::Atom;
@@ -165,9 +213,10 @@ where
     /// assert_eq!(a().get(), 0, "The a state be reset to initial value");
     /// ```
     pub fn reset_to_default(&self) {
-        (clone_reactive_state_with_id::<RxFunc>(self.id)
-            .unwrap()
-            .func)();
+        run_reactive_func(
+            self.id,
+            &clone_reactive_state_with_id::<RxFunc>(self.id).unwrap(),
+        );
         execute_reaction_nodes(&self.id);
     }
 
@@ -411,11 +460,16 @@ where
 
 use crate::{
     clone_reactive_state_with_id,
-    reactive_state_access::{CloneReactiveState, ObserveChangeReactiveState},
+    middleware::{intercept_writes_with_id, AtomWrite},
+    reactive_state_access::{lens::Lens, CloneReactiveState, ObserveChangeReactiveState},
     reactive_state_exists_for_id,
-    reactive_state_functions::{execute_reaction_nodes, set_atom_state_with_id},
+    reactive_state_functions::{
+        debug_name_of, execute_reaction_nodes, find_by_debug_name, run_reactive_func,
+        set_atom_state_with_id,
+    },
     read_reactive_state_with_id, remove_reactive_state_with_id, set_inert_atom_state_with_id,
-    store::StorageKey,
+    store::{NodeKind, StorageKey},
+    transaction::journal_write,
     update_atom_state_with_id, Observable, RxFunc,
 };
 use std::{
@@ -517,6 +571,39 @@ mod test {
         c.get()
     }
 
+    #[atom(memo)]
+    fn memo_a() -> Atom<i32> {
+        0
+    }
+
+    #[atom]
+    fn memo_runs() -> Atom<i32> {
+        0
+    }
+
+    #[reaction]
+    fn memo_a_doubled() -> Reaction<i32> {
+        memo_runs().update(|v| *v += 1);
+        memo_a().observe() * 2
+    }
+
+    #[test]
+    fn test_memo_atom_skips_equal_values() {
+        let doubled = memo_a_doubled();
+        assert_eq!(memo_runs().get(), 1);
+
+        memo_a().set(0);
+        memo_a().update(|v| *v += 0);
+        assert_eq!(memo_runs().get(), 1, "Equal values should not propagate");
+
+        memo_a().set(4);
+        assert_eq!(memo_runs().get(), 2);
+        assert_eq!(doubled.get(), 8);
+
+        memo_a().update(|v| *v = 4);
+        assert_eq!(memo_runs().get(), 2, "Equal values should not propagate");
+    }
+
     #[test]
     fn test_set_atom() {
         let a = a();
@@ -658,4 +745,60 @@ mod test {
         let a_1 = a.clone();
         assert_eq!(a_1.get(), 8, "We should get 8 as value on the copy as well")
     }
+
+    #[test]
+    fn test_subscribe_on_atom() {
+        let changes = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let subscribed_changes = changes.clone();
+        let subscription = a().subscribe(move |old, new| {
+            subscribed_changes.borrow_mut().push((*old, *new));
+        });
+
+        a().set(1);
+        a().update(|v| *v += 2);
+        drop(subscription);
+        a().set(10);
+
+        assert_eq!(*changes.borrow(), vec![(0, 1), (1, 3)]);
+    }
+
+    #[test]
+    fn test_atoms_in_separate_stores() {
+        let first_store = StoreHandle::new();
+        let second_store = StoreHandle::new();
+
+        first_store.enter(|| a().set(1));
+        second_store.enter(|| a().set(2));
+        a().set(3);
+
+        assert_eq!(first_store.enter(|| a().get()), 1);
+        assert_eq!(second_store.enter(|| a().get()), 2);
+        assert_eq!(a().get(), 3);
+
+        let subtraction = first_store.enter(|| {
+            let subtraction = a_b_subtraction();
+            b().set(5);
+            subtraction.get()
+        });
+        assert_eq!(subtraction, -4);
+        assert_eq!(a_b_subtraction().get(), 3, "the default store is untouched");
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_store_forwards_to_the_current_store() {
+        let store = StoreHandle::new();
+        let value_in = |id| {
+            crate::reactive_state_functions::STORE.with(|store_refcell| {
+                store_refcell
+                    .borrow()
+                    .get_state_with_id::<i32>(&id)
+                    .copied()
+            })
+        };
+        store.enter(|| a().set(7));
+
+        assert_eq!(store.enter(|| value_in(a().id)), Some(7));
+        assert_ne!(value_in(a().id), Some(7), "the default store is untouched");
+    }
 }
@@ -0,0 +1,263 @@
+use crate::{
+    helpers::CallSite,
+    reactive_state_functions::{run_in_reactive_context, run_reactive_func, with_store},
+    store::{NodeKind, RxFunc, SlottedKey, StorageKey},
+};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+/// A handle to a side effect created with [`effect`] or
+/// [`effect_with_cleanup`].
+///
+/// The effect keeps running until it is disposed, dropping the handle does
+/// not stop it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Effect {
+    pub id: StorageKey,
+}
+
+// the cleanup returned by the last run of an effect
+struct EffectCleanup(Box<dyn FnOnce()>);
+
+/// Runs `func` now and again whenever anything it observes changes.
+///
+/// Effects run once propagation has completed, so every reaction they
+/// observe is already up to date, and only once per [`batch`].
+///
+/// ```
+/// use atomic_hooks::{atom::Atom, *};
+/// #[atom]
+/// fn count() -> Atom<i32> {
+///     0
+/// }
+///
+/// let logger = effect(|| println!("count is {}", count().observe()));
+/// count().set(1);
+/// logger.dispose();
+/// ```
+///
+/// [`batch`]: crate::batch
+#[track_caller]
+pub fn effect<F: Fn() + 'static>(func: F) -> Effect {
+    effect_with_cleanup(move || {
+        func();
+        || {}
+    })
+}
+
+/// Like [`effect`], but `func` returns a cleanup closure that runs before
+/// the next run of the effect and when the effect is disposed.
+#[track_caller]
+pub fn effect_with_cleanup<F, C>(func: F) -> Effect
+where
+    F: Fn() -> C + 'static,
+    C: FnOnce() + 'static,
+{
+    let mut hasher = DefaultHasher::new();
+    CallSite::here().hash(&mut hasher);
+
+    let id = with_store(|store_refcell| {
+        let mut store = store_refcell.borrow_mut();
+        // every call creates a new effect, even from the same location
+        store.next_effect_slot += 1;
+        let id = StorageKey::SlottedKey(SlottedKey {
+            location: hasher.finish(),
+            slot: store.next_effect_slot,
+        });
+        let key = store.primary_slotmap.insert(id);
+        store.id_to_key_map.insert(id, key);
+        store.set_state_with_id(NodeKind::Effect, &id);
+        id
+    });
+
+    let effect = RxFunc::new(move || {
+        run_cleanup(id);
+        let cleanup = run_in_reactive_context(id, &func);
+        with_store(|store_refcell| {
+            store_refcell
+                .borrow_mut()
+                .set_state_with_id(EffectCleanup(Box::new(cleanup)), &id)
+        });
+    });
+
+    with_store(|store_refcell| store_refcell.borrow_mut().new_reaction(&id, effect.clone()));
+    run_reactive_func(id, &effect);
+
+    Effect { id }
+}
+
+fn run_cleanup(id: StorageKey) {
+    let cleanup = with_store(|store_refcell| {
+        store_refcell
+            .borrow_mut()
+            .remove_state_with_id::<EffectCleanup>(&id)
+    });
+    if let Some(EffectCleanup(cleanup)) = cleanup {
+        cleanup();
+    }
+}
+
+impl Effect {
+    /// Whether the effect is still running on changes.
+    pub fn is_active(&self) -> bool {
+        with_store(|store_refcell| {
+            store_refcell.borrow().kind_of(&self.id) == Some(NodeKind::Effect)
+        })
+    }
+
+    /// Stops the effect and runs its last cleanup.
+    pub fn dispose(self) {
+        if !self.is_active() {
+            return;
+        }
+
+        with_store(|store_refcell| {
+            let mut store = store_refcell.borrow_mut();
+            store.remove_dependencies_of(&self.id);
+            store.pending_effects.retain(|id| *id != self.id);
+        });
+        run_cleanup(self.id);
+        with_store(|store_refcell| store_refcell.borrow_mut().remove_id(&self.id));
+    }
+}
+
+/// Unsubscribes the callback passed to [`Observable::subscribe`] when it is
+/// dropped.
+///
+/// [`Observable::subscribe`]: crate::Observable::subscribe
+#[must_use = "the subscription is cancelled as soon as it is dropped"]
+#[derive(Debug)]
+pub struct Subscription {
+    effect: Effect,
+}
+
+impl Subscription {
+    pub(crate) fn new(effect: Effect) -> Subscription {
+        Subscription { effect }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.effect.is_active()
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.effect.dispose();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        reactive_state_access::{atom::Atom, reaction::Reaction},
+        *,
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    #[atom]
+    fn temperature() -> Atom<i32> {
+        20
+    }
+
+    #[atom]
+    fn unit() -> Atom<&'static str> {
+        "C"
+    }
+
+    #[reaction]
+    fn reading() -> Reaction<String> {
+        format!("{}{}", temperature().observe(), unit().observe())
+    }
+
+    #[test]
+    fn test_effect_runs_after_changes() {
+        let log = Rc::new(RefCell::new(vec![]));
+        let effect_log = log.clone();
+        let logger = effect(move || effect_log.borrow_mut().push(reading().observe()));
+
+        temperature().set(21);
+        unit().set("F");
+
+        assert_eq!(*log.borrow(), vec!["20C", "21C", "21F"]);
+        logger.dispose();
+    }
+
+    #[test]
+    fn test_effect_runs_once_per_batch() {
+        let log = Rc::new(RefCell::new(vec![]));
+        let effect_log = log.clone();
+        let logger = effect(move || effect_log.borrow_mut().push(reading().observe()));
+
+        batch(|| {
+            temperature().set(25);
+            unit().set("K");
+        });
+
+        assert_eq!(*log.borrow(), vec!["20C", "25K"]);
+        logger.dispose();
+    }
+
+    #[test]
+    fn test_effect_cleanup_and_dispose() {
+        let log = Rc::new(RefCell::new(vec![]));
+        let effect_log = log.clone();
+        let logger = effect_with_cleanup(move || {
+            let value = temperature().observe();
+            effect_log.borrow_mut().push(format!("run {}", value));
+            let cleanup_log = effect_log.clone();
+            move || cleanup_log.borrow_mut().push(format!("cleanup {}", value))
+        });
+
+        temperature().set(30);
+        assert!(logger.is_active());
+        logger.dispose();
+        assert!(!logger.is_active());
+        assert!(
+            with_store(|store_refcell| !store_refcell
+                .borrow()
+                .id_to_key_map
+                .contains_key(&logger.id)),
+            "the effect is removed from the store"
+        );
+        temperature().set(31);
+
+        assert_eq!(
+            *log.borrow(),
+            vec!["run 20", "cleanup 20", "run 30", "cleanup 30"]
+        );
+    }
+
+    #[reaction]
+    fn fragile_reading() -> Reaction<String> {
+        let reading = reading().observe();
+        if reading == "-273C" {
+            panic!("below absolute zero");
+        }
+        reading
+    }
+
+    #[test]
+    fn test_effects_run_after_a_caught_panic() {
+        fragile_reading();
+        let log = Rc::new(RefCell::new(vec![]));
+        let effect_log = log.clone();
+        let logger = effect(move || effect_log.borrow_mut().push(unit().observe()));
+        let failed_effect = effect(|| {
+            if temperature().observe() == 0 {
+                panic!("frozen");
+            }
+        });
+
+        assert!(std::panic::catch_unwind(|| temperature().set(-273)).is_err());
+        assert!(std::panic::catch_unwind(|| temperature().set(0)).is_err());
+        unit().set("F");
+
+        assert_eq!(*log.borrow(), vec!["C", "F"]);
+        logger.dispose();
+        failed_effect.dispose();
+    }
+}
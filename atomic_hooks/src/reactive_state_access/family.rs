@@ -0,0 +1,235 @@
+use crate::{
+    reactive_state_access::{
+        atom::Atom, observable::Observable, reaction::Reaction, CloneReactiveState,
+    },
+    reactive_state_functions::{
+        atom, batch, name_reactive_state_with_id, reaction, reactive_state_exists_for_id,
+        return_key_for_name_and_insert_if_required, run_in_reactive_context,
+        set_inert_atom_state_with_id, with_store,
+    },
+    ssr::stable_hash,
+    store::{SlottedKey, StorageKey},
+};
+
+/// Every atom created from an `#[atom(family)]` function, keyed by its
+/// arguments. A single argument is its own key, several are a tuple.
+///
+/// ```
+/// use atomic_hooks::{atom::Atom, *};
+/// #[atom(family)]
+/// fn todo_done(id: u32) -> Atom<bool> {
+///     false
+/// }
+///
+/// todo_done(1);
+/// todo_done(2).set(true);
+/// assert_eq!(todo_done_family().keys(), vec![1, 2]);
+///
+/// todo_done_family().remove(&1);
+/// assert_eq!(todo_done_family().values(), vec![true]);
+/// ```
+pub struct AtomFamily<K, T> {
+    pub id: StorageKey,
+    name: &'static str,
+    member: fn(K) -> Atom<T>,
+}
+
+impl<K, T> std::fmt::Debug for AtomFamily<K, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({:#?})", self.id)
+    }
+}
+
+impl<K, T> Clone for AtomFamily<K, T> {
+    fn clone(&self) -> AtomFamily<K, T> {
+        *self
+    }
+}
+
+impl<K, T> Copy for AtomFamily<K, T> {}
+
+// marks an atom that has been added to the keys of its family
+struct FamilyMember;
+
+impl<K, T> AtomFamily<K, T>
+where
+    K: 'static + Clone + PartialEq,
+    T: 'static,
+{
+    /// The family named `name`, whose members are created by `member`.
+    ///
+    /// Typically this is used via `#[atom(family)]`.
+    pub fn new(name: &'static str, member: fn(K) -> Atom<T>) -> AtomFamily<K, T> {
+        let id = return_key_for_name_and_insert_if_required(name, ());
+        atom::<Vec<K>, _>(id, move || {
+            set_inert_atom_state_with_id(Vec::<K>::new(), id)
+        });
+        AtomFamily { id, name, member }
+    }
+
+    // the keys of every member, observed by the whole family
+    fn keys_atom(&self) -> Atom<Vec<K>> {
+        Atom::new(self.id)
+    }
+
+    /// Adds the atom for `id` to the family under `key`, unless it already is.
+    ///
+    /// Typically this is used via `#[atom(family)]`.
+    pub fn register_member(&self, key: K, id: StorageKey) {
+        if reactive_state_exists_for_id::<FamilyMember>(id) {
+            return;
+        }
+        set_inert_atom_state_with_id(FamilyMember, id);
+        self.keys_atom().update(|keys| {
+            if !keys.contains(&key) {
+                keys.push(key)
+            }
+        });
+    }
+
+    /// The member for `key`, created if it does not exist yet.
+    pub fn get(&self, key: K) -> Atom<T> {
+        (self.member)(key)
+    }
+
+    /// The keys of every member in the order they were created.
+    pub fn keys(&self) -> Vec<K> {
+        self.keys_atom().get()
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.keys_atom().get_with(|keys| keys.contains(key))
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys_atom().get_with(Vec::len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every member in the order they were created.
+    pub fn members(&self) -> Vec<Atom<T>> {
+        self.keys().into_iter().map(self.member).collect()
+    }
+
+    /// Removes the member for `key` and its state. Returns whether it
+    /// existed.
+    pub fn remove(&self, key: &K) -> bool {
+        if !self.contains(key) {
+            return false;
+        }
+        let id = self.get(key.clone()).id;
+        with_store(|store_refcell| store_refcell.borrow_mut().remove_id(&id));
+        self.keys_atom()
+            .update(|keys| keys.retain(|member| member != key));
+        true
+    }
+
+    /// Removes every member and its state.
+    pub fn clear(&self) {
+        batch(|| {
+            for member in self.members() {
+                with_store(|store_refcell| store_refcell.borrow_mut().remove_id(&member.id));
+            }
+            self.keys_atom().set(vec![]);
+        })
+    }
+}
+
+impl<K, T> AtomFamily<K, T>
+where
+    K: 'static + Clone + PartialEq,
+    T: 'static + Clone,
+{
+    /// The value of every member in the order they were created.
+    pub fn values(&self) -> Vec<T> {
+        self.members()
+            .into_iter()
+            .map(|member| member.get())
+            .collect()
+    }
+
+    /// Observes the value of every member, and the family itself so that
+    /// the reaction re-runs when members are added or removed.
+    pub fn observe_all(&self) -> Vec<(K, T)> {
+        self.keys_atom()
+            .observe()
+            .into_iter()
+            .map(|key| (key.clone(), self.get(key).observe()))
+            .collect()
+    }
+
+    /// A reaction over the whole family, see [`observe_all`](Self::observe_all).
+    pub fn all(&self) -> Reaction<Vec<(K, T)>> {
+        // next to the family's own key, which has no arguments and so slot 0
+        let id = StorageKey::SlottedKey(SlottedKey {
+            location: stable_hash(self.name),
+            slot: 1,
+        });
+        let family = *self;
+        name_reactive_state_with_id(id, self.name);
+        reaction::<Vec<(K, T)>, _>(id, move || {
+            let all = run_in_reactive_context(id, || family.observe_all());
+            set_inert_atom_state_with_id(all, id)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{reactive_state_access::atom::Atom, *};
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Todo {
+        title: String,
+        done: bool,
+    }
+
+    #[atom(family)]
+    fn todo(list: &'static str, index: usize) -> Atom<Todo> {
+        Todo {
+            title: format!("{} {}", list, index),
+            done: false,
+        }
+    }
+
+    #[test]
+    fn test_family_keys_remove_and_clear() {
+        let todos = todo_family();
+        todo("home", 1);
+        todo("work", 1);
+        todo("home", 1);
+        assert_eq!(todos.keys(), vec![("home", 1), ("work", 1)]);
+
+        todo("home", 1).update(|todo| todo.done = true);
+        assert!(todos.remove(&("home", 1)));
+        assert!(!todos.remove(&("home", 1)));
+        assert_eq!(todos.len(), 1);
+        assert!(!todo("home", 1).get().done, "removed state is reset");
+
+        todos.clear();
+        assert!(todos.is_empty());
+    }
+
+    #[test]
+    fn test_family_reaction() {
+        let todos = todo_family();
+        let all = todos.all();
+        assert!(all.get().is_empty());
+
+        todo("home", 1);
+        todo("home", 2).update(|todo| todo.done = true);
+        let done = |all: Vec<(_, Todo)>| all.iter().filter(|(_, todo)| todo.done).count();
+        assert_eq!(all.get().len(), 2);
+        assert_eq!(done(all.get()), 1);
+
+        todo("home", 1).update(|todo| todo.done = true);
+        assert_eq!(done(all.get()), 2);
+
+        todos.remove(&("home", 2));
+        assert_eq!(all.get().len(), 1);
+    }
+}
@@ -0,0 +1,229 @@
+use crate::{
+    helpers::CallSite,
+    reactive_state_access::{atom::Atom, observable::Observable, CloneReactiveState},
+    reactive_state_functions::{
+        clone_reactive_state_with_id, debug_name_of, memoize_reactive_state_with_id, reaction,
+        reactive_state_exists_for_id, recompute_if_dirty, run_in_reactive_context,
+        set_inert_atom_state_with_id, with_store,
+    },
+    store::{SlottedKey, StorageKey},
+};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+/// A field of the value of an atom, created with [`Atom::map_field`].
+///
+/// A lens is a memoized reaction over its atom, so reactions observing it
+/// only re-run when the field itself changes. Setting it writes the field
+/// back into the atom.
+///
+/// ```
+/// use atomic_hooks::{atom::Atom, *};
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct Settings {
+///     theme: String,
+///     font_size: u8,
+/// }
+///
+/// #[atom]
+/// fn settings() -> Atom<Settings> {
+///     Settings {
+///         theme: "light".to_string(),
+///         font_size: 12,
+///     }
+/// }
+///
+/// let theme = settings().map_field("theme", |settings| &mut settings.theme);
+/// theme.set("dark".to_string());
+/// assert_eq!(settings().get().theme, "dark");
+/// assert_eq!(theme.get(), "dark");
+/// ```
+pub struct Lens<S, F> {
+    pub id: StorageKey,
+    source: Atom<S>,
+    project: fn(&mut S) -> &mut F,
+}
+
+impl<S, F> std::fmt::Debug for Lens<S, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({:#?})", self.id)
+    }
+}
+
+impl<S, F> Clone for Lens<S, F> {
+    fn clone(&self) -> Lens<S, F> {
+        *self
+    }
+}
+
+impl<S, F> Copy for Lens<S, F> {}
+
+impl<S, F> Lens<S, F>
+where
+    S: 'static + Clone,
+    F: 'static + Clone + PartialEq,
+{
+    /// The lens of `project` onto `source` at the calling location, created if
+    /// it does not exist yet. `field` tells apart the lenses created at a
+    /// single location, function pointers are not reliable for that.
+    #[track_caller]
+    pub fn new(source: Atom<S>, field: &'static str, project: fn(&mut S) -> &mut F) -> Lens<S, F> {
+        let mut hasher = DefaultHasher::new();
+        CallSite::here().hash(&mut hasher);
+        source.id.hash(&mut hasher);
+        field.hash(&mut hasher);
+        let id = StorageKey::SlottedKey(SlottedKey {
+            location: hasher.finish(),
+            slot: 0,
+        });
+        with_store(|store_refcell| store_refcell.borrow_mut().insert_seen_id(id));
+
+        if !reactive_state_exists_for_id::<F>(id) {
+            memoize_reactive_state_with_id::<F>(id);
+            reaction::<F, _>(id, move || {
+                let field = run_in_reactive_context(id, || project(&mut source.observe()).clone());
+                set_inert_atom_state_with_id(field, id);
+            });
+        }
+
+        Lens {
+            id,
+            source,
+            project,
+        }
+    }
+
+    /// The debug name of the lens, the key of its reaction.
+    pub fn debug_name(&self) -> String {
+        debug_name_of(self.id)
+    }
+
+    /// Writes `value` into the field of the atom.
+    pub fn set(self, value: F) {
+        let project = self.project;
+        self.source.update(|source| *project(source) = value);
+    }
+
+    /// Updates the field of the atom in place.
+    pub fn update<U: FnOnce(&mut F)>(&self, func: U) {
+        let project = self.project;
+        self.source.update(|source| func(project(source)));
+    }
+}
+
+impl<S, F> Observable<F> for Lens<S, F>
+where
+    F: 'static,
+{
+    fn id(&self) -> StorageKey {
+        self.id
+    }
+}
+
+impl<S, F> CloneReactiveState<F> for Lens<S, F>
+where
+    F: Clone + 'static,
+{
+    fn get(&self) -> F {
+        recompute_if_dirty(self.id);
+        clone_reactive_state_with_id::<F>(self.id).expect("state should be present")
+    }
+
+    fn soft_get(&self) -> Option<F> {
+        recompute_if_dirty(self.id);
+        clone_reactive_state_with_id::<F>(self.id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        reactive_state_access::{atom::Atom, reaction::Reaction},
+        *,
+    };
+    use std::cell::Cell;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Profile {
+        name: String,
+        city: String,
+        visits: u32,
+    }
+
+    #[atom]
+    fn profile() -> Atom<Profile> {
+        Profile {
+            name: "ada".to_string(),
+            city: "london".to_string(),
+            visits: 0,
+        }
+    }
+
+    fn profile_name() -> Lens<Profile, String> {
+        profile().map_field("name", |profile| &mut profile.name)
+    }
+
+    thread_local! {
+        static GREETING_RUNS: Cell<u32> = const { Cell::new(0) };
+    }
+
+    #[reaction]
+    fn greeting() -> Reaction<String> {
+        GREETING_RUNS.with(|runs| runs.set(runs.get() + 1));
+        format!("Hello {}", profile_name().observe())
+    }
+
+    #[test]
+    fn test_lens_only_propagates_field_changes() {
+        let greeting = greeting();
+        assert_eq!(GREETING_RUNS.with(Cell::get), 1);
+
+        profile().update(|profile| profile.visits += 1);
+        assert_eq!(
+            GREETING_RUNS.with(Cell::get),
+            1,
+            "other fields do not re-run the reaction"
+        );
+
+        profile_name().set("grace".to_string());
+        assert_eq!(profile().get().visits, 1);
+        assert_eq!(greeting.get(), "Hello grace");
+        assert_eq!(GREETING_RUNS.with(Cell::get), 2);
+
+        profile_name().update(|name| name.make_ascii_uppercase());
+        assert_eq!(greeting.get(), "Hello GRACE");
+    }
+
+    #[test]
+    fn test_lenses_from_one_call_site_keep_their_fields_apart() {
+        type Project = fn(&mut Profile) -> &mut String;
+        let projections: [(&str, Project); 2] = [
+            ("name", |profile| &mut profile.name),
+            ("city", |profile| &mut profile.city),
+        ];
+        let lenses = projections
+            .iter()
+            .map(|(field, project)| profile().map_field(field, *project))
+            .collect::<Vec<_>>();
+
+        assert_ne!(lenses[0].id, lenses[1].id);
+        assert_eq!(lenses[0].get(), "ada");
+        assert_eq!(lenses[1].get(), "london");
+    }
+
+    #[test]
+    fn test_lenses_survive_garbage_collection() {
+        let name = profile_name();
+        name.set("grace".to_string());
+
+        reset_unseen_reactive_id_list();
+        let name = profile_name();
+        purge_unseen_reactive_ids();
+
+        assert!(reactive_state_exists_for_id::<String>(name.id));
+        assert_eq!(name.get(), "grace");
+    }
+}
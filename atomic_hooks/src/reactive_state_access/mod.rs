@@ -1,8 +1,12 @@
 // If the stored type is clone, then implement clone for ReactiveStateAccess
 pub mod atom;
+pub mod effect;
+pub mod family;
+pub mod lens;
 pub mod observable;
 pub mod reaction;
 pub mod reversible_atom;
+pub mod selector;
 pub mod state_access;
 
 pub trait CloneReactiveState<T>
@@ -1,6 +1,7 @@
+use crate::reactive_state_access::effect::{effect, Subscription};
 use crate::reactive_state_access::state_access::CloneState;
 use crate::reactive_state_access::CloneReactiveState;
-use crate::reactive_state_functions::STORE;
+use crate::reactive_state_functions::{recompute_if_dirty, with_store};
 use crate::store::StorageKey;
 use crate::{clone_reactive_state_with_id, read_reactive_state_with_id, ReactiveContext};
 use std::cell::RefCell;
@@ -23,12 +24,13 @@ where
             .reactive_state_accessors
             .push(self.id());
 
-        STORE.with(|store_refcell| {
+        with_store(|store_refcell| {
             store_refcell
                 .borrow_mut()
                 .add_dependency(&self.id(), &context.borrow().key);
         });
 
+        recompute_if_dirty(self.id());
         clone_reactive_state_with_id::<T>(self.id()).unwrap()
     }
     #[topo::nested]
@@ -50,12 +52,46 @@ where
                 .reactive_state_accessors
                 .push(self.id());
 
-            STORE.with(|store_refcell| {
+            with_store(|store_refcell| {
                 store_refcell
                     .borrow_mut()
                     .add_dependency(&self.id(), &context.borrow().key);
             });
         }
+        recompute_if_dirty(self.id());
         read_reactive_state_with_id(self.id(), func)
     }
+    /// Calls `func` with the previous and the new value every time the state
+    /// changes, until the returned [`Subscription`] is dropped.
+    ///
+    /// `func` is called on every write, or for a reaction on every re-run,
+    /// even when the new value equals the previous one. Compare `old` and
+    /// `new` in `func`, or use a `memo` atom or reaction, to skip those calls.
+    ///
+    /// ```
+    /// use atomic_hooks::{atom::Atom, *};
+    /// #[atom]
+    /// fn messages() -> Atom<u32> {
+    ///     0
+    /// }
+    ///
+    /// let _subscription =
+    ///     messages().subscribe(|old, new| println!("{} new messages", new - old));
+    /// messages().set(3);
+    /// ```
+    fn subscribe<F: Fn(&T, &T) + 'static>(&self, func: F) -> Subscription
+    where
+        T: Clone,
+        Self: Clone + 'static,
+    {
+        let source = self.clone();
+        let previous = RefCell::new(None::<T>);
+        let effect = effect(move || {
+            let new_value = source.observe();
+            if let Some(old_value) = previous.replace(Some(new_value.clone())) {
+                func(&old_value, &new_value);
+            }
+        });
+        Subscription::new(effect)
+    }
 }
@@ -1,6 +1,11 @@
 use crate::{
-    clone_reactive_state_with_id, reactive_state_exists_for_id, read_reactive_state_with_id,
-    remove_reactive_state_with_id, store::StorageKey, Observable, RxFunc,
+    clone_reactive_state_with_id, reactive_state_exists_for_id,
+    reactive_state_functions::{
+        debug_name_of, find_by_debug_name, recompute_if_dirty, run_reactive_func,
+    },
+    read_reactive_state_with_id, remove_reactive_state_with_id,
+    store::{NodeKind, StorageKey},
+    Observable, RxFunc,
 };
 
 use crate::reactive_state_access::{
@@ -13,6 +18,10 @@ use std::marker::PhantomData;
 /// a new value which you can observe. The new value will get automatically
 /// updated as long as the update on the atom is not **inert**.  
 ///
+/// A reaction declared with `#[reaction(lazy)]` is not recomputed when its
+/// dependencies change, it is only marked as dirty and recomputed the next
+/// time it is read or observed.
+///
 /// ```
 /// use atomic_hooks::{atom::Atom, reaction::Reaction, Observable};
 /// #[atom]
@@ -105,6 +114,18 @@ where
             _phantom_data_stored_type: PhantomData,
         }
     }
+
+    /// The function path the reaction was declared with, followed by the hash of
+    /// its arguments if it has any.
+    pub fn debug_name(&self) -> String {
+        debug_name_of(self.id)
+    }
+
+    /// Finds the reaction with the given [`debug_name`](Reaction::debug_name), if
+    /// it has been created and holds a `T`.
+    pub fn by_name(debug_name: &str) -> Option<Reaction<T>> {
+        find_by_debug_name::<T>(debug_name, NodeKind::Reaction).map(Reaction::new)
+    }
     /// Remove the reaction from the global state
     /// ```
     /// use atomic_hooks::{atom::Atom, reaction::Reaction, Observable};
@@ -178,9 +199,10 @@ where
     /// - I thought the new value was updated automatically, isn't ?
     /// - When & why to use this method ?
     pub fn force_trigger(&self) {
-        (clone_reactive_state_with_id::<RxFunc>(self.id)
-            .unwrap()
-            .func)();
+        run_reactive_func(
+            self.id,
+            &clone_reactive_state_with_id::<RxFunc>(self.id).unwrap(),
+        );
     }
     /// Check if the state_exist
     /// ```
@@ -249,6 +271,7 @@ where
     /// }
     /// ```
     pub fn get_with<F: FnOnce(&T) -> R, R>(&self, func: F) -> R {
+        recompute_if_dirty(self.id);
         read_reactive_state_with_id(self.id, func)
     }
     /// Triggers the passed function when the atom is updated
@@ -547,10 +570,12 @@ where
 {
     /// returns a clone of the stored state panics if not stored.
     fn get(&self) -> T {
+        recompute_if_dirty(self.id);
         clone_reactive_state_with_id::<T>(self.id).expect("state should be present")
     }
 
     fn soft_get(&self) -> Option<T> {
+        recompute_if_dirty(self.id);
         clone_reactive_state_with_id::<T>(self.id)
     }
 }
@@ -560,6 +585,7 @@ mod test {
     use super::*;
     use crate::{
         reactive_state_access::{atom::Atom, reaction::Reaction, reversible_atom::ReversibleAtom},
+        reactive_state_functions::with_store,
         *,
     };
 
@@ -612,6 +638,110 @@ mod test {
         c.get()
     }
 
+    #[reaction]
+    fn a_plus_one() -> Reaction<i32> {
+        a().observe() + 1
+    }
+
+    #[reaction]
+    fn a_times_two() -> Reaction<i32> {
+        a().observe() * 2
+    }
+
+    #[atom]
+    fn diamond_observations() -> Atom<Vec<(i32, i32, i32)>> {
+        vec![]
+    }
+
+    #[reaction]
+    fn diamond_bottom() -> Reaction<i32> {
+        let a = a().observe();
+        let plus_one = a_plus_one().observe();
+        let times_two = a_times_two().observe();
+        diamond_observations().update(|v| v.push((a, plus_one, times_two)));
+        plus_one + times_two
+    }
+
+    #[atom]
+    fn lazy_runs() -> Atom<i32> {
+        0
+    }
+
+    #[reaction(lazy)]
+    fn lazy_a_b_subtraction() -> Reaction<i32> {
+        lazy_runs().update(|v| *v += 1);
+        a().observe() - b().observe()
+    }
+
+    #[reaction]
+    fn lazy_subtraction_doubled() -> Reaction<i32> {
+        lazy_a_b_subtraction().observe() * 2
+    }
+
+    #[reaction(memo)]
+    fn a_is_positive() -> Reaction<bool> {
+        a().observe() > 0
+    }
+
+    #[atom]
+    fn positive_runs() -> Atom<i32> {
+        0
+    }
+
+    #[reaction]
+    fn positive_label() -> Reaction<&'static str> {
+        positive_runs().update(|v| *v += 1);
+        if a_is_positive().observe() {
+            "positive"
+        } else {
+            "not positive"
+        }
+    }
+
+    #[atom]
+    fn read_a() -> Atom<bool> {
+        true
+    }
+
+    #[atom]
+    fn branch_runs() -> Atom<i32> {
+        0
+    }
+
+    #[reaction]
+    fn a_or_b() -> Reaction<i32> {
+        branch_runs().update(|v| *v += 1);
+        if read_a().observe() {
+            a().observe()
+        } else {
+            b().observe()
+        }
+    }
+
+    #[atom]
+    fn user_name(id: u32) -> Atom<String> {
+        format!("user {}", id)
+    }
+
+    #[reaction]
+    fn user_greeting(id: u32) -> Reaction<String> {
+        format!("Hello {}", user_name(id).observe())
+    }
+
+    #[reaction]
+    fn observes_itself() -> Reaction<i32> {
+        observes_itself().observe() + 1
+    }
+
+    #[reaction]
+    fn fails_on_thirteen() -> Reaction<i32> {
+        let a = a().observe();
+        if a == 13 {
+            panic!("unlucky");
+        }
+        a
+    }
+
     #[atom(reversible)]
     fn a_reversible() -> ReversibleAtom<i32> {
         0
@@ -705,6 +835,178 @@ mod test {
         assert_eq!(count.get(), 5, "We should get 5 update counted");
     }
 
+    #[test]
+    fn test_diamond_reaction_runs_once_per_update() {
+        let bottom = diamond_bottom();
+        diamond_observations().set(vec![]);
+
+        a().set(1);
+        assert_eq!(bottom.get(), 4);
+        assert_eq!(
+            diamond_observations().get(),
+            vec![(1, 2, 2)],
+            "The bottom reaction should run once and only see up to date values"
+        );
+
+        a().update(|v| *v = 5);
+        assert_eq!(bottom.get(), 16);
+        assert_eq!(diamond_observations().get(), vec![(1, 2, 2), (5, 6, 10)]);
+    }
+
+    #[test]
+    fn test_batch_propagates_once() {
+        let bottom = diamond_bottom();
+        let subtraction = a_b_subtraction();
+        diamond_observations().set(vec![]);
+
+        batch(|| {
+            a().set(1);
+            a().set(2);
+            b().set(5);
+            assert_eq!(
+                subtraction.get(),
+                0,
+                "Reactions should not run inside a batch"
+            );
+        });
+
+        assert_eq!(subtraction.get(), -3);
+        assert_eq!(bottom.get(), 7);
+        assert_eq!(diamond_observations().get(), vec![(2, 3, 4)]);
+    }
+
+    #[test]
+    fn test_nested_batch() {
+        let subtraction = a_b_subtraction();
+
+        batch(|| {
+            a().set(10);
+            batch(|| b().set(4));
+            assert_eq!(subtraction.get(), 0, "Only the outermost batch propagates");
+        });
+
+        assert_eq!(subtraction.get(), 6);
+    }
+
+    #[test]
+    fn test_batch_ends_on_panic() {
+        let subtraction = a_b_subtraction();
+        let result = std::panic::catch_unwind(|| {
+            batch(|| {
+                a().set(5);
+                panic!("interrupted")
+            })
+        });
+        assert!(result.is_err());
+        assert_eq!(
+            subtraction.get(),
+            5,
+            "The changes made before the panic are propagated"
+        );
+
+        a().set(3);
+        assert_eq!(
+            subtraction.get(),
+            3,
+            "Propagation is not deferred after a panic"
+        );
+    }
+
+    #[test]
+    fn test_lazy_reaction_recomputes_on_read() {
+        let subtraction = lazy_a_b_subtraction();
+        assert_eq!(
+            lazy_runs().get(),
+            0,
+            "A lazy reaction should not run until read"
+        );
+
+        assert_eq!(subtraction.get(), 0);
+        assert_eq!(lazy_runs().get(), 1);
+
+        a().set(5);
+        a().set(7);
+        b().set(2);
+        assert_eq!(lazy_runs().get(), 1, "Changes should only mark it as dirty");
+
+        assert_eq!(subtraction.get(), 5);
+        subtraction.get_with(|v| assert_eq!(v, &5));
+        assert_eq!(lazy_runs().get(), 2, "It should recompute once when read");
+    }
+
+    #[test]
+    fn test_lazy_reaction_recomputes_when_observed() {
+        let doubled = lazy_subtraction_doubled();
+        assert_eq!(doubled.get(), 0);
+
+        a().set(3);
+        assert_eq!(
+            doubled.get(),
+            6,
+            "An eager reaction observing a lazy one should see its new value"
+        );
+    }
+
+    #[test]
+    fn test_memo_reaction_stops_propagation() {
+        let label = positive_label();
+        assert_eq!(positive_runs().get(), 1);
+
+        a().set(1);
+        assert_eq!(label.get(), "positive");
+        assert_eq!(positive_runs().get(), 2);
+
+        a().set(2);
+        a().set(3);
+        assert_eq!(
+            positive_runs().get(),
+            2,
+            "The memoized reaction did not change so its dependents should not run"
+        );
+
+        a().set(-1);
+        assert_eq!(label.get(), "not positive");
+        assert_eq!(positive_runs().get(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "reactive dependency cycle detected: \
+                               atomic_hooks::reactive_state_access::reaction::test::observes_itself")]
+    fn test_self_observing_reaction_panics() {
+        observes_itself();
+    }
+
+    #[test]
+    fn test_reaction_runs_after_a_caught_panic() {
+        let reaction = fails_on_thirteen();
+        let result = std::panic::catch_unwind(|| a().set(13));
+        assert!(result.is_err());
+
+        a().set(2);
+        assert_eq!(reaction.get(), 2, "A panic should not leave a false cycle");
+    }
+
+    #[test]
+    fn test_try_add_dependency_reports_cycle() {
+        let plus_one = a_plus_one();
+        let a = a();
+
+        let cycle = with_store(|store_refcell| {
+            store_refcell
+                .borrow_mut()
+                .try_add_dependency(&plus_one.id, &a.id)
+                .unwrap_err()
+        });
+
+        let names = cycle
+            .chain
+            .iter()
+            .map(|(_, name)| name.unwrap().rsplit("::").next().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["a_plus_one", "a", "a_plus_one"]);
+        assert_eq!(cycle.chain[0].0, plus_one.id);
+    }
+
     #[test]
     fn test_inert_set() {
         a_reversible().inert_set(155);
@@ -808,4 +1110,107 @@ mod test {
             "We should get 20 for subtraction because setting"
         );
     }
+
+    #[test]
+    fn test_subscribe_on_reaction() {
+        let changes = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let subscribed_changes = changes.clone();
+        let subscription = a_b_subtraction().subscribe(move |old, new| {
+            subscribed_changes.borrow_mut().push((*old, *new));
+        });
+
+        batch(|| {
+            a().set(5);
+            b().set(2);
+        });
+        b().set(5);
+
+        assert_eq!(*changes.borrow(), vec![(0, 3), (3, 0)]);
+        assert!(subscription.is_active());
+    }
+
+    #[test]
+    fn test_branch_switching_re_tracks_dependencies() {
+        let a_or_b = a_or_b();
+        assert_eq!(branch_runs().get(), 1);
+
+        b().set(3);
+        assert_eq!(branch_runs().get(), 1, "b is not read yet");
+
+        read_a().set(false);
+        assert_eq!(a_or_b.get(), 3);
+        assert_eq!(branch_runs().get(), 2);
+
+        a().set(5);
+        assert_eq!(branch_runs().get(), 2, "a is not read anymore");
+        b().set(4);
+        assert_eq!(a_or_b.get(), 4);
+        assert_eq!(branch_runs().get(), 3);
+
+        let a_id = a().id;
+        let dependents_of_a =
+            with_store(|store_refcell| store_refcell.borrow().dependent_ids(&a_id));
+        assert!(!dependents_of_a.contains(&a_or_b.id));
+
+        read_a().set(true);
+        a().set(6);
+        assert_eq!(a_or_b.get(), 6);
+        assert_eq!(branch_runs().get(), 5);
+    }
+
+    #[test]
+    fn test_purge_unseen_reactive_ids() {
+        let greeting_1 = user_greeting(1);
+        let greeting_2 = user_greeting(2);
+        let name_2 = user_name(2);
+        name_2.set("renamed".to_string());
+
+        reset_unseen_reactive_id_list();
+        user_greeting(1);
+        let mut removed = purge_unseen_reactive_ids();
+        removed.sort_by_key(|id| format!("{:?}", id));
+        let mut expected = vec![greeting_2.id, name_2.id];
+        expected.sort_by_key(|id| format!("{:?}", id));
+        assert_eq!(
+            removed, expected,
+            "user 1 is still observed by its greeting"
+        );
+
+        assert!(!greeting_2.state_exists());
+        user_name(1).set("Ada".to_string());
+        assert_eq!(greeting_1.get(), "Hello Ada");
+        assert_eq!(user_name(2).get(), "user 2", "user 2 starts over");
+    }
+
+    #[test]
+    fn test_debug_name_and_lookup() {
+        let greeting = user_greeting(7);
+        let name = greeting.debug_name();
+        assert_eq!(
+            name,
+            format!(
+                "atomic_hooks::reactive_state_access::reaction::test::user_greeting#{:016x}",
+                stable_hash(&(7_u32,))
+            )
+        );
+        assert_eq!(
+            Reaction::<String>::by_name(&name).map(|reaction| reaction.id),
+            Some(greeting.id)
+        );
+        assert!(Atom::<String>::by_name(&name).is_none(), "it is a reaction");
+        assert!(
+            Reaction::<i32>::by_name(&name).is_none(),
+            "it holds a String"
+        );
+
+        assert_eq!(
+            a().debug_name(),
+            "atomic_hooks::reactive_state_access::reaction::test::a"
+        );
+        assert_eq!(
+            a_reversible().debug_name(),
+            "atomic_hooks::reactive_state_access::reaction::test::a_reversible"
+        );
+        assert!(ReversibleAtom::<i32>::by_name(&a_reversible().debug_name()).is_some());
+    }
 }
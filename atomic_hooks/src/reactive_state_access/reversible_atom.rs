@@ -1,13 +1,18 @@
 use crate::{
     clone_reactive_state_with_id,
+    middleware::{intercept_writes_with_id, AtomWrite},
     reactive_state_access::CloneReactiveState,
     reactive_state_exists_for_id,
     reactive_state_functions::{
-        execute_reaction_nodes, remove_reactive_reversible_state_with_id,
-        set_atom_reversible_state_with_id, update_atom_reversible_state_with_id,
+        debug_name_of, execute_reaction_nodes, find_by_debug_name,
+        patch_atom_reversible_state_with_id, remove_reactive_reversible_state_with_id,
+        run_reactive_func, set_atom_reversible_state_with_id, update_atom_reversible_state_with_id,
     },
-    read_reactive_state_with_id, set_inert_atom_reversible_state_with_id,
-    store::StorageKey,
+    read_reactive_state_with_id,
+    reverse::Reversible,
+    set_inert_atom_reversible_state_with_id,
+    store::{NodeKind, StorageKey},
+    transaction::journal_write,
     Observable, RxFunc,
 };
 
@@ -109,6 +114,18 @@ where
         }
     }
 
+    /// The function path the atom was declared with, followed by the hash of
+    /// its arguments if it has any.
+    pub fn debug_name(&self) -> String {
+        debug_name_of(self.id)
+    }
+
+    /// Finds the atom with the given [`debug_name`](ReversibleAtom::debug_name), if
+    /// it has been created and holds a `T`.
+    pub fn by_name(debug_name: &str) -> Option<ReversibleAtom<T>> {
+        find_by_debug_name::<T>(debug_name, NodeKind::Atom).map(ReversibleAtom::new)
+    }
+
     /// Stores a value of type T in a backing Store **without** reaction for
     /// observers.
     ///
@@ -145,6 +162,7 @@ where
     where
         T: 'static,
     {
+        journal_write(self.id);
         set_inert_atom_reversible_state_with_id(value, self.id);
     }
     /// ```
@@ -186,6 +204,21 @@ where
         update_atom_reversible_state_with_id(self.id, func);
     }
 
+    /// Calls `func` on every write to the atom, which may change the value
+    /// written or veto it, see [`intercept_writes_with_id`].
+    pub fn intercept<F: Fn(&AtomWrite, &mut T) -> bool + 'static>(&self, func: F) {
+        intercept_writes_with_id(self.id, func);
+    }
+
+    /// Applies `patch` to the value. Only the patch and its inverse are
+    /// recorded in the undo history, see [`Reversible`].
+    pub fn patch(&self, patch: T::Patch)
+    where
+        T: Reversible,
+    {
+        patch_atom_reversible_state_with_id::<T>(self.id, patch);
+    }
+
     /// ```
     /// use atomic_hooks::reversible_atom::ReversibleAtom;
     /// #[atom(reversible)]
@@ -231,9 +264,10 @@ where
     /// assert_eq!(a().get(), 0, "The a state be reset to initial value");
     /// ```
     pub fn reset_to_default(&self) {
-        (clone_reactive_state_with_id::<RxFunc>(self.id)
-            .unwrap()
-            .func)();
+        run_reactive_func(
+            self.id,
+            &clone_reactive_state_with_id::<RxFunc>(self.id).unwrap(),
+        );
         execute_reaction_nodes(&self.id);
     }
     /// ```
@@ -331,6 +365,10 @@ mod test {
     fn b_reversible() -> ReversibleAtom<i32> {
         0
     }
+    #[atom(reversible, memo)]
+    fn memo_reversible() -> ReversibleAtom<i32> {
+        0
+    }
     #[reaction]
     fn a_b_reversible_subtraction() -> Reaction<i32> {
         let a = a_reversible().observe();
@@ -371,6 +409,43 @@ mod test {
 
         assert_eq!(a_reversible().get(), 0, "We should get 0 as value for a");
     }
+    #[test]
+    fn test_batch_is_a_single_undo_step() {
+        let subtraction = a_b_reversible_subtraction();
+        a_reversible().set(1);
+
+        batch(|| {
+            a_reversible().set(3);
+            b_reversible().set(2);
+            a_reversible().update(|v| *v += 4);
+        });
+        assert_eq!(subtraction.get(), 5);
+
+        global_reverse_queue().travel_backwards();
+        assert_eq!(a_reversible().get(), 1, "The whole batch should be undone");
+        assert_eq!(b_reversible().get(), 0, "The whole batch should be undone");
+
+        global_reverse_queue().travel_forwards();
+        assert_eq!(a_reversible().get(), 7, "The whole batch should be redone");
+        assert_eq!(b_reversible().get(), 2, "The whole batch should be redone");
+        assert_eq!(subtraction.get(), 5);
+    }
+
+    #[test]
+    fn test_memo_skips_undo_of_equal_values() {
+        memo_reversible().set(2);
+        memo_reversible().set(2);
+        memo_reversible().update(|v| *v *= 1);
+        assert_eq!(
+            global_reverse_queue().len(),
+            1,
+            "Equal writes are not undoable"
+        );
+
+        global_reverse_queue().travel_backwards();
+        assert_eq!(memo_reversible().get(), 0);
+    }
+
     #[test]
     fn test_update() {
         a_reversible().set(10);
@@ -403,4 +478,18 @@ mod test {
             "The state  a_reversible should not exist"
         );
     }
+
+    #[test]
+    fn test_subscribe_on_reversible_atom() {
+        let changes = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let subscribed_changes = changes.clone();
+        let _subscription = a_reversible().subscribe(move |old, new| {
+            subscribed_changes.borrow_mut().push((*old, *new));
+        });
+
+        a_reversible().set(7);
+        a_reversible().update(|v| *v *= 2);
+
+        assert_eq!(*changes.borrow(), vec![(0, 7), (7, 14)]);
+    }
 }
@@ -0,0 +1,206 @@
+use crate::{
+    reactive_state_access::{observable::Observable, CloneReactiveState},
+    reactive_state_functions::{
+        batch, clone_reactive_state_with_id, debug_name_of, find_by_debug_name, recompute_if_dirty,
+        set_inert_atom_state_with_id, with_store,
+    },
+    store::{NodeKind, StorageKey},
+};
+use std::{marker::PhantomData, rc::Rc};
+
+/// A reaction that can also be written to. Its value is derived from the
+/// atoms it observes, and setting it calls a setter that writes back into
+/// them.
+///
+/// ```
+/// use atomic_hooks::{atom::Atom, *};
+/// #[atom]
+/// fn first_name() -> Atom<String> {
+///     "Ada".to_string()
+/// }
+///
+/// #[atom]
+/// fn last_name() -> Atom<String> {
+///     "Lovelace".to_string()
+/// }
+///
+/// #[selector(set = "set_full_name")]
+/// fn full_name() -> Selector<String> {
+///     format!("{} {}", first_name().observe(), last_name().observe())
+/// }
+///
+/// fn set_full_name(full_name: String) {
+///     let mut names = full_name.splitn(2, ' ');
+///     first_name().set(names.next().unwrap_or_default().to_string());
+///     last_name().set(names.next().unwrap_or_default().to_string());
+/// }
+///
+/// full_name().set("Grace Hopper".to_string());
+/// assert_eq!(first_name().get(), "Grace");
+/// assert_eq!(full_name().get(), "Grace Hopper");
+/// ```
+pub struct Selector<T> {
+    pub id: StorageKey,
+    pub _phantom_data_stored_type: PhantomData<T>,
+}
+
+impl<T> std::fmt::Debug for Selector<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({:#?})", self.id)
+    }
+}
+
+impl<T> Clone for Selector<T> {
+    fn clone(&self) -> Selector<T> {
+        *self
+    }
+}
+
+impl<T> Copy for Selector<T> {}
+
+// writes a new value of a selector back into its sources
+struct SelectorSetter<T>(Rc<dyn Fn(T)>);
+
+/// Stores the setter for the selector `id`.
+///
+/// Typically this is used via `#[selector(set = "...")]`.
+pub fn register_selector_setter<T: 'static, F: Fn(T) + 'static>(id: StorageKey, setter: F) {
+    set_inert_atom_state_with_id(SelectorSetter::<T>(Rc::new(setter)), id);
+}
+
+impl<T> Selector<T>
+where
+    T: 'static,
+{
+    pub fn new(id: StorageKey) -> Selector<T> {
+        Selector {
+            id,
+            _phantom_data_stored_type: PhantomData,
+        }
+    }
+
+    /// The function path the selector was declared with, followed by the
+    /// hash of its arguments if it has any.
+    pub fn debug_name(&self) -> String {
+        debug_name_of(self.id)
+    }
+
+    /// Finds the selector with the given
+    /// [`debug_name`](Selector::debug_name), if it has been created and holds
+    /// a `T`.
+    pub fn by_name(debug_name: &str) -> Option<Selector<T>> {
+        find_by_debug_name::<T>(debug_name, NodeKind::Reaction).map(Selector::new)
+    }
+
+    /// Passes `value` to the setter. Dependent reactions re-run once, after
+    /// every atom it writes has been updated.
+    pub fn set(self, value: T) {
+        let setter = with_store(|store_refcell| {
+            store_refcell
+                .borrow()
+                .get_state_with_id::<SelectorSetter<T>>(&self.id)
+                .map(|setter| setter.0.clone())
+        })
+        .expect("Selector has no setter, was it created with #[selector(set = \"...\")]?");
+        batch(|| setter(value))
+    }
+
+    /// Updates a copy of the current value with `func` and sets it.
+    pub fn update<F: FnOnce(&mut T)>(&self, func: F)
+    where
+        T: Clone,
+    {
+        let mut value = self.get();
+        func(&mut value);
+        self.set(value)
+    }
+}
+
+impl<T> Observable<T> for Selector<T>
+where
+    T: 'static,
+{
+    fn id(&self) -> StorageKey {
+        self.id
+    }
+}
+
+impl<T> CloneReactiveState<T> for Selector<T>
+where
+    T: Clone + 'static,
+{
+    fn get(&self) -> T {
+        recompute_if_dirty(self.id);
+        clone_reactive_state_with_id::<T>(self.id).expect("state should be present")
+    }
+
+    fn soft_get(&self) -> Option<T> {
+        recompute_if_dirty(self.id);
+        clone_reactive_state_with_id::<T>(self.id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        reactive_state_access::{atom::Atom, reaction::Reaction},
+        *,
+    };
+
+    #[atom]
+    fn celsius() -> Atom<f64> {
+        0.0
+    }
+
+    #[selector(set = "set_fahrenheit")]
+    fn fahrenheit() -> Selector<f64> {
+        celsius().observe() * 9.0 / 5.0 + 32.0
+    }
+
+    fn set_fahrenheit(fahrenheit: f64) {
+        celsius().set((fahrenheit - 32.0) * 5.0 / 9.0)
+    }
+
+    #[atom]
+    fn tag(index: usize) -> Atom<String> {
+        format!("tag {}", index)
+    }
+
+    #[selector(set = "set_shouted_tag")]
+    fn shouted_tag(index: usize) -> Selector<String> {
+        tag(index).observe().to_uppercase()
+    }
+
+    fn set_shouted_tag(index: usize, shouted: String) {
+        tag(index).set(shouted.to_lowercase())
+    }
+
+    #[reaction]
+    fn weather_report() -> Reaction<String> {
+        format!("{}F", fahrenheit().observe())
+    }
+
+    #[test]
+    fn test_selector_writes_back() {
+        let report = weather_report();
+        assert_eq!(report.get(), "32F");
+
+        fahrenheit().set(212.0);
+        assert_eq!(celsius().get(), 100.0);
+        assert_eq!(fahrenheit().get(), 212.0);
+        assert_eq!(report.get(), "212F");
+
+        fahrenheit().update(|fahrenheit| *fahrenheit -= 180.0);
+        assert_eq!(celsius().get(), 0.0);
+    }
+
+    #[test]
+    fn test_selector_with_arguments() {
+        assert_eq!(shouted_tag(1).get(), "TAG 1");
+        shouted_tag(2).set("NEW".to_string());
+        assert_eq!(tag(2).get(), "new");
+        assert_eq!(tag(1).get(), "tag 1");
+        assert_eq!(shouted_tag(2).get(), "NEW");
+    }
+}
@@ -1,4 +1,9 @@
-use crate::{hooks_state_functions::*, store::TopoKey, Observable};
+use crate::{
+    hooks_state_functions::*,
+    middleware::{intercept_set, intercept_writes_with_id, AtomWrite},
+    store::TopoKey,
+    Observable,
+};
 use std::marker::PhantomData;
 
 ///  Accessor struct that provides access to getting and setting the
@@ -38,7 +43,18 @@ where
 
     // stores a value of type T in a backing Store
     pub fn set(self, value: T) {
-        set_state_with_topo_id(value, self.id);
+        if let Some(value) = intercept_set(StorageKey::TopoKey(self.id), value) {
+            set_state_with_topo_id(value, self.id);
+        }
+    }
+
+    /// Calls `func` on every write to the state, see
+    /// [`intercept_writes_with_id`].
+    pub fn intercept<F: Fn(&AtomWrite, &mut T) -> bool + 'static>(self, func: F)
+    where
+        T: Clone,
+    {
+        intercept_writes_with_id(StorageKey::TopoKey(self.id), func);
     }
 
     pub fn remove(self) -> Option<T> {
@@ -1,14 +1,50 @@
 use crate::{
+    middleware::{intercept_patch, intercept_set, intercept_update},
     reactive_state_access::{atom::Atom, reaction::Reaction, reversible_atom::ReversibleAtom},
-    reverse::global_reverse_queue,
-    store::{ReactiveContext, RxFunc, SlottedKey, StorageKey, Store},
+    reverse::{group_batched_undo_commands, record_undo_command_in, undo_history_of, Reversible},
+    ssr::stable_hash,
+    store::{
+        Memo, NodeKind, ReactiveContext, RxFunc, SlottedKey, StateName, StorageKey, Store,
+        StoreHandle,
+    },
+    transaction::journal_write,
 };
-use std::{cell::RefCell, hash::Hash, rc::Rc};
+use std::{any::TypeId, cell::RefCell, collections::HashSet, hash::Hash, rc::Rc};
 
 // use seed::{*,prelude};
 
 thread_local! {
-    pub static STORE: RefCell<Store> = RefCell::new(Store::new());
+    static STORES: RefCell<Vec<StoreHandle>> = RefCell::new(vec![StoreHandle::new()]);
+}
+
+/// Calls `func` with the store that is currently in scope, the thread's
+/// default store unless inside [`StoreHandle::enter`] or [`Store::scope`].
+pub fn with_store<F: FnOnce(&RefCell<Store>) -> R, R>(func: F) -> R {
+    func(&current_store().0)
+}
+
+/// The former thread local store, which now forwards to [`with_store`].
+pub struct CurrentStore;
+
+impl CurrentStore {
+    pub fn with<F: FnOnce(&RefCell<Store>) -> R, R>(&'static self, func: F) -> R {
+        with_store(func)
+    }
+}
+
+#[deprecated(note = "use `with_store`, which also sees the stores entered with `StoreHandle`")]
+pub static STORE: CurrentStore = CurrentStore;
+
+pub(crate) fn current_store() -> StoreHandle {
+    STORES.with(|stores| stores.borrow().last().cloned().unwrap())
+}
+
+pub(crate) fn push_store(store: StoreHandle) {
+    STORES.with(|stores| stores.borrow_mut().push(store));
+}
+
+pub(crate) fn pop_store() {
+    STORES.with(|stores| stores.borrow_mut().pop());
 }
 
 //
@@ -25,20 +61,24 @@ thread_local! {
 //
 pub fn atom<T: 'static, F: Fn() -> () + 'static>(id: StorageKey, data_fn: F) -> Atom<T> {
     // we do not need to re-initalize the atom if it already has been stored.
-    if !reactive_state_exists_for_id::<T>(id) {
+    let initialized = reactive_state_exists_for_id::<T>(id);
+    // hydrated atoms have a state but still need their initializer registered
+    if !initialized || !reactive_state_exists_for_id::<RxFunc>(id) {
         let reaction = RxFunc {
             func: Rc::new(data_fn),
         };
 
-        STORE.with(|store_refcell| {
+        with_store(|store_refcell| {
             store_refcell
                 .borrow_mut()
                 .new_reaction(&id, reaction.clone());
         });
 
-        (reaction.func.clone())();
+        if !initialized {
+            run_reactive_func(id, &reaction);
+        }
 
-        STORE.with(|store_refcell| {
+        with_store(|store_refcell| {
             store_refcell.borrow_mut().add_atom(&id);
         })
     }
@@ -50,31 +90,25 @@ pub fn atom_reverse<T: 'static + Clone, F: Fn() -> () + 'static>(
     data_fn: F,
 ) -> ReversibleAtom<T> {
     // we do not need to re-initalize the atom if it already has been stored.
-    if !reactive_state_exists_for_id::<T>(id) {
+    let initialized = reactive_state_exists_for_id::<T>(id);
+    // hydrated atoms have a state but still need their initializer registered
+    if !initialized || !reactive_state_exists_for_id::<RxFunc>(id) {
         let reaction = RxFunc {
             func: Rc::new(data_fn),
         };
 
-        STORE.with(|store_refcell| {
+        with_store(|store_refcell| {
             store_refcell
                 .borrow_mut()
                 .new_reaction(&id, reaction.clone());
         });
 
-        (reaction.func.clone())();
-
-        crate::reverse::global_reverse_queue().update(|u| {
-            u.commands.push(crate::reverse::Command::new(
-                reaction,
-                RxFunc {
-                    func: Rc::new(move || {
-                        remove_reactive_state_with_id::<T>(id);
-                    }),
-                },
-            ))
-        });
+        // creating the atom is not an undoable change
+        if !initialized {
+            run_reactive_func(id, &reaction);
+        }
 
-        STORE.with(|store_refcell| {
+        with_store(|store_refcell| {
             store_refcell.borrow_mut().add_atom(&id);
         })
     }
@@ -95,23 +129,26 @@ pub fn atom_reverse<T: 'static + Clone, F: Fn() -> () + 'static>(
 //
 pub fn reaction<T: 'static, F: Fn() -> () + 'static>(id: StorageKey, data_fn: F) -> Reaction<T> {
     if !reactive_state_exists_for_id::<T>(id) {
-        STORE.with(|store_refcell| {
-            let key = store_refcell.borrow_mut().primary_slotmap.insert(id);
-
-            store_refcell.borrow_mut().id_to_key_map.insert(id, key);
+        with_store(|store_refcell| {
+            let mut store = store_refcell.borrow_mut();
+            if !store.id_to_key_map.contains_key(&id) {
+                let key = store.primary_slotmap.insert(id);
+                store.id_to_key_map.insert(id, key);
+            }
+            store.set_state_with_id(NodeKind::Reaction, &id);
         });
 
         let reaction = RxFunc {
             func: Rc::new(data_fn),
         };
 
-        STORE.with(|store_refcell| {
+        with_store(|store_refcell| {
             store_refcell
                 .borrow_mut()
                 .new_reaction(&id, reaction.clone());
         });
 
-        (reaction.func.clone())();
+        run_reactive_func(id, &reaction);
     }
 
     Reaction::<T>::new(id)
@@ -122,17 +159,20 @@ pub fn reaction_start_suspended<T: 'static, F: Fn() -> () + 'static>(
     data_fn: F,
 ) -> Reaction<T> {
     if !reactive_state_exists_for_id::<T>(id) {
-        STORE.with(|store_refcell| {
-            let key = store_refcell.borrow_mut().primary_slotmap.insert(id);
-
-            store_refcell.borrow_mut().id_to_key_map.insert(id, key);
+        with_store(|store_refcell| {
+            let mut store = store_refcell.borrow_mut();
+            if !store.id_to_key_map.contains_key(&id) {
+                let key = store.primary_slotmap.insert(id);
+                store.id_to_key_map.insert(id, key);
+            }
+            store.set_state_with_id(NodeKind::Reaction, &id);
         });
 
         let reaction = RxFunc {
             func: Rc::new(data_fn),
         };
 
-        STORE.with(|store_refcell| {
+        with_store(|store_refcell| {
             store_refcell
                 .borrow_mut()
                 .new_reaction(&id, reaction.clone());
@@ -142,109 +182,226 @@ pub fn reaction_start_suspended<T: 'static, F: Fn() -> () + 'static>(
     Reaction::<T>::new(id)
 }
 
+//
+//  Constructs a lazy T reaction state accessor. Unlike a regular reaction the
+// passed closure is not run when a dependency changes, the reaction is only
+// marked as dirty and recomputed the next time it is read or observed.
+//
+// Typically this is created via the #[reaction(lazy)] attribute macro
+//
+pub fn reaction_lazy<T: 'static, F: Fn() -> () + 'static>(
+    id: StorageKey,
+    data_fn: F,
+) -> Reaction<T> {
+    if !reactive_state_exists_for_id::<RxFunc>(id) {
+        with_store(|store_refcell| {
+            let mut store = store_refcell.borrow_mut();
+            store.new_reaction(&id, RxFunc::new(data_fn));
+            store.set_state_with_id(NodeKind::Reaction, &id);
+            store.lazy_ids.insert(id);
+            store.dirty_ids.insert(id);
+        });
+    }
+
+    Reaction::<T>::new(id)
+}
+
+/// Recomputes a lazy reaction if one of its dependencies changed since it was
+/// last read.
+pub(crate) fn recompute_if_dirty(id: StorageKey) {
+    let dirty = with_store(|store_refcell| store_refcell.borrow_mut().dirty_ids.remove(&id));
+
+    if dirty {
+        if let Some(reaction) = clone_reactive_state_with_id::<RxFunc>(id) {
+            run_reactive_func(id, &reaction);
+        }
+    }
+}
+
+/// Runs the function stored for a reactive state.
+///
+/// Panics with the chain of states involved if `id` is already being computed
+/// further up the stack, i.e. if it (indirectly) depends on itself.
+pub(crate) fn run_reactive_func(id: StorageKey, reaction: &RxFunc) {
+    with_store(|store_refcell| {
+        let mut store = store_refcell.borrow_mut();
+        if let Some(position) = store.running_ids.iter().position(|running| *running == id) {
+            let mut chain = store.running_ids[position..].to_vec();
+            chain.push(id);
+            let cycle = store.dependency_cycle(chain);
+            drop(store);
+            panic!("{}", cycle);
+        }
+        store.running_ids.push(id);
+    });
+
+    // leaves the running reaction even if it panics
+    struct StopRunning;
+    impl Drop for StopRunning {
+        fn drop(&mut self) {
+            with_store(|store_refcell| {
+                store_refcell.borrow_mut().running_ids.pop();
+            });
+        }
+    }
+
+    let _stop_running = StopRunning;
+    (reaction.func.clone())();
+}
+
+/// Records the name of the function that declared the state for `id`, so that
+/// diagnostics can refer to it.
+///
+/// Typically this is called by the `#[atom]` and `#[reaction]` macros.
+pub fn name_reactive_state_with_id(id: StorageKey, name: &'static str) {
+    if !reactive_state_exists_for_id::<StateName>(id) {
+        set_inert_atom_state_with_id(StateName(name), id);
+    }
+}
+
+/// Returns the debug name of `id`, see [`Store::debug_name_of`], or its key
+/// if it has no name.
+pub fn debug_name_of(id: StorageKey) -> String {
+    with_store(|store_refcell| store_refcell.borrow().debug_name_of(&id))
+        .unwrap_or_else(|| format!("{:?}", id))
+}
+
+/// Returns the id with the given debug name, see [`Store::debug_name_of`].
+pub fn id_by_debug_name(debug_name: &str) -> Option<StorageKey> {
+    with_store(|store_refcell| store_refcell.borrow().id_by_debug_name(debug_name))
+}
+
+// the id with the given debug name, if it is of `kind` and holds a `T`
+pub(crate) fn find_by_debug_name<T: 'static>(
+    debug_name: &str,
+    kind: NodeKind,
+) -> Option<StorageKey> {
+    let id = id_by_debug_name(debug_name)?;
+    let is_kind = with_store(|store_refcell| store_refcell.borrow().kind_of(&id) == Some(kind));
+    if is_kind && reactive_state_exists_for_id::<T>(id) {
+        Some(id)
+    } else {
+        None
+    }
+}
+
+/// Removes the dependencies of `id` that its current run no longer observes
+/// and remembers what it did observe for the next run.
+///
+/// Called at the end of every reaction run, so a reaction only re-runs for the
+/// branches it actually read last time.
 pub fn unlink_dead_links(id: StorageKey) {
     let context = illicit::get::<RefCell<ReactiveContext>>().expect(
         "No #[reaction] context found, are you sure you are in one? I.e. does the current \
          function have a #[reaction] tag?",
     );
-    if reactive_state_exists_for_id::<ReactiveContext>(id) {
-        read_reactive_state_with_id::<ReactiveContext, _, ()>(id, |old_context| {
-            let ids_to_remove = old_context
+    let context = context.borrow().clone();
+
+    if let Some(old_context) = remove_reactive_state_with_id::<ReactiveContext>(id) {
+        with_store(|store_refcell| {
+            let mut store = store_refcell.borrow_mut();
+            for id_to_remove in old_context
                 .reactive_state_accessors
                 .iter()
-                .filter(|a_id| !context.borrow().reactive_state_accessors.contains(a_id));
-            for id_to_remove in ids_to_remove {
-                STORE.with(|store_refcell| {
-                    store_refcell
-                        .borrow_mut()
-                        .remove_dependency(id_to_remove, &id);
-                })
+                .filter(|a_id| !context.reactive_state_accessors.contains(a_id))
+            {
+                store.remove_dependency(id_to_remove, &id);
             }
         })
-    } else {
-        set_inert_atom_state_with_id::<ReactiveContext>(context.borrow().clone(), id)
     }
+    set_inert_atom_state_with_id::<ReactiveContext>(context, id)
 }
 
 /// Sets the state of type T keyed to the given TopoId
 pub fn set_inert_atom_state_with_id<T: 'static>(data: T, id: StorageKey) {
-    STORE.with(|store_refcell| store_refcell.borrow_mut().set_state_with_id::<T>(data, &id))
+    with_store(|store_refcell| store_refcell.borrow_mut().set_state_with_id::<T>(data, &id))
 }
 
 /// Sets the state of type T keyed to the given TopoId
 pub fn set_inert_atom_reversible_state_with_id<T: 'static + Clone>(data: T, id: StorageKey) {
-    let new_data = data.clone();
     if let Some(previous_state) = clone_reactive_state_with_id::<T>(id) {
-        global_reverse_queue().update(|u| {
-            u.commands.truncate(u.cursor);
-
-            u.commands.push(crate::reverse::Command::new(
+        let new_data = data.clone();
+        record_undo_command_in(
+            undo_history_of(id),
+            crate::reverse::Command::new(
                 RxFunc::new(move || {
                     set_inert_atom_state_with_id::<T>(new_data.clone(), id);
                 }),
                 RxFunc::new(move || {
                     set_inert_atom_state_with_id::<T>(previous_state.clone(), id);
                 }),
-            ));
-            u.cursor += 1;
-        })
+            ),
+        )
     }
 
-    STORE.with(|store_refcell| store_refcell.borrow_mut().set_state_with_id::<T>(data, &id))
+    with_store(|store_refcell| store_refcell.borrow_mut().set_state_with_id::<T>(data, &id))
 }
 
 /// Sets the state of type T keyed to the given TopoId
 pub fn set_atom_state_with_id<T: 'static>(data: T, id: StorageKey) {
-    STORE.with(|store_refcell| store_refcell.borrow_mut().set_state_with_id::<T>(data, &id));
+    if let Some(data) = intercept_set(id, data) {
+        restore_atom_state_with_id(data, id);
+    }
+}
+
+/// Writes a previous value back to the state `id` and re-runs its dependent
+/// reactions, without passing it through interceptors or write middleware.
+///
+/// Used by undo, redo and rolled back transactions, which must not be vetoed
+/// halfway through.
+pub(crate) fn restore_atom_state_with_id<T: 'static>(data: T, id: StorageKey) {
+    let changed = write_changes_state(id, || {
+        with_store(|store_refcell| store_refcell.borrow_mut().set_state_with_id::<T>(data, &id))
+    });
 
-    execute_reaction_nodes(&id);
+    if changed {
+        execute_reaction_nodes(&id);
+    }
 }
 
 /// Sets the state of type T keyed to the given TopoId
 pub fn set_atom_reversible_state_with_id<T: 'static + Clone>(data: T, id: StorageKey) {
+    let data = match intercept_set(id, data) {
+        Some(data) => data,
+        None => return,
+    };
     let new_data = data.clone();
-    if let Some(previous_state) = clone_reactive_state_with_id::<T>(id) {
-        global_reverse_queue().update(|u| {
-            u.commands.truncate(u.cursor);
+    let previous_state = clone_reactive_state_with_id::<T>(id);
 
-            u.commands.push(crate::reverse::Command::new(
-                RxFunc::new(move || {
-                    set_atom_state_with_id::<T>(new_data.clone(), id);
-                }),
-                RxFunc::new(move || {
-                    set_inert_atom_state_with_id::<T>(previous_state.clone(), id);
-                }),
-            ));
-            u.cursor += 1;
-        })
-    } else {
-        global_reverse_queue().update(|u| {
-            u.commands.truncate(u.cursor);
+    let changed = write_changes_state(id, || {
+        with_store(|store_refcell| store_refcell.borrow_mut().set_state_with_id::<T>(data, &id))
+    });
 
-            u.commands.push(crate::reverse::Command::new(
-                RxFunc::new(move || {
-                    set_atom_state_with_id::<T>(new_data.clone(), id);
-                }),
+    // a memoized atom set to an equal value has nothing to undo
+    if changed {
+        let undo = match previous_state {
+            Some(previous_state) => RxFunc::new(move || {
+                restore_atom_state_with_id::<T>(previous_state.clone(), id);
+            }),
+            None => RxFunc::new(move || {
+                remove_reactive_state_with_id::<T>(id);
+            }),
+        };
+        record_undo_command_in(
+            undo_history_of(id),
+            crate::reverse::Command::new(
                 RxFunc::new(move || {
-                    remove_reactive_state_with_id::<T>(id);
+                    restore_atom_state_with_id::<T>(new_data.clone(), id);
                 }),
-            ));
-            u.cursor += 1;
-        })
+                undo,
+            ),
+        );
+        execute_reaction_nodes(&id);
     }
-
-    STORE.with(|store_refcell| store_refcell.borrow_mut().set_state_with_id::<T>(data, &id));
-
-    execute_reaction_nodes(&id);
 }
 
 pub fn reactive_state_exists_for_id<T: 'static>(id: StorageKey) -> bool {
-    STORE.with(|store_refcell| store_refcell.borrow().state_exists_with_id::<T>(id))
+    with_store(|store_refcell| store_refcell.borrow().state_exists_with_id::<T>(id))
 }
 
 /// Clones the state of type T keyed to the given TopoId
 pub fn clone_reactive_state_with_id<T: 'static + Clone>(id: StorageKey) -> Option<T> {
-    STORE.with(|store_refcell| {
+    with_store(|store_refcell| {
         store_refcell
             .borrow_mut()
             .get_state_with_id::<T>(&id)
@@ -253,62 +410,309 @@ pub fn clone_reactive_state_with_id<T: 'static + Clone>(id: StorageKey) -> Optio
 }
 
 pub fn remove_reactive_state_with_id<T: 'static>(id: StorageKey) -> Option<T> {
-    STORE.with(|store_refcell| store_refcell.borrow_mut().remove_state_with_id::<T>(&id))
+    with_store(|store_refcell| store_refcell.borrow_mut().remove_state_with_id::<T>(&id))
 }
 
 pub fn remove_reactive_reversible_state_with_id<T: 'static + Clone>(id: StorageKey) -> Option<T> {
     if let Some(previous_state) = clone_reactive_state_with_id::<T>(id) {
-        global_reverse_queue().update(|u| {
-            u.commands.truncate(u.cursor);
-
-            u.commands.push(crate::reverse::Command::new(
+        record_undo_command_in(
+            undo_history_of(id),
+            crate::reverse::Command::new(
                 RxFunc::new(move || {
                     remove_reactive_state_with_id::<T>(id);
                 }),
                 RxFunc::new(move || {
                     set_inert_atom_state_with_id::<T>(previous_state.clone(), id);
                 }),
-            ));
-            u.cursor += 1;
-        })
-    } else {
-        global_reverse_queue().update(|u| {
-            u.cursor += 1;
-        })
+            ),
+        )
     }
 
-    STORE.with(|store_refcell| store_refcell.borrow_mut().remove_state_with_id::<T>(&id))
+    with_store(|store_refcell| store_refcell.borrow_mut().remove_state_with_id::<T>(&id))
 }
 
 #[derive(Clone)]
 pub struct UndoVec<T>(pub Vec<T>);
 
+/// Re-runs every reaction that depends on `id`, directly or transitively.
+///
+/// Reactions are run once each, in topological order, so a reaction is only
+/// recomputed after all of the reactions it observes have been updated.
+/// Lazy reactions are only marked as dirty and effects are run once every
+/// reaction is up to date. Inside a [`batch`] the id is only recorded and
+/// propagated when the outermost batch ends.
 pub fn execute_reaction_nodes(id: &StorageKey) {
-    let ids_reactions = STORE.with(|refcell_store| {
+    let deferred = with_store(|refcell_store| {
         let mut borrow = refcell_store.borrow_mut();
-        borrow.clone_dep_funcs_for_id(id)
+        if borrow.batch_depth > 0 {
+            if !borrow.batched_ids.contains(id) {
+                borrow.batched_ids.push(*id);
+            }
+            true
+        } else {
+            false
+        }
+    });
+
+    if !deferred {
+        execute_reaction_nodes_for_ids(&[*id]);
+    }
+}
+
+fn execute_reaction_nodes_for_ids(ids: &[StorageKey]) {
+    // leaves the propagation even if a reaction panics
+    struct EndPropagation;
+    impl Drop for EndPropagation {
+        fn drop(&mut self) {
+            with_store(|refcell_store| refcell_store.borrow_mut().propagation_depth -= 1);
+        }
+    }
+
+    let ids_reactions = with_store(|refcell_store| {
+        let mut borrow = refcell_store.borrow_mut();
+        borrow.propagation_depth += 1;
+        borrow.clone_dep_funcs_in_order(ids)
+    });
+    let end_propagation = EndPropagation;
+
+    // only reactions with a dependency that actually changed are re-run
+    let mut scheduled = with_store(|refcell_store| {
+        let borrow = refcell_store.borrow();
+        ids.iter()
+            .flat_map(|id| borrow.dependent_ids(id))
+            .collect::<HashSet<StorageKey>>()
     });
 
     for (key, reaction) in &ids_reactions {
-        let cloned_reaction = reaction.clone();
-        (cloned_reaction.func.clone())();
-        execute_reaction_nodes(&key);
+        if !scheduled.contains(key) {
+            continue;
+        }
+
+        // lazy reactions are marked as dirty and effects wait for the
+        // propagation to complete
+        let deferred = with_store(|refcell_store| {
+            let mut borrow = refcell_store.borrow_mut();
+            if borrow.lazy_ids.contains(key) {
+                borrow.dirty_ids.insert(*key);
+                true
+            } else if borrow.kind_of(key) == Some(NodeKind::Effect) {
+                if !borrow.pending_effects.contains(key) {
+                    borrow.pending_effects.push(*key);
+                }
+                true
+            } else {
+                false
+            }
+        });
+
+        let changed = deferred || write_changes_state(*key, || run_reactive_func(*key, reaction));
+
+        if changed {
+            with_store(|refcell_store| {
+                scheduled.extend(refcell_store.borrow().dependent_ids(key));
+            });
+        }
+    }
+
+    drop(end_propagation);
+    let completed = with_store(|refcell_store| refcell_store.borrow().propagation_depth == 0);
+
+    if completed {
+        run_pending_effects();
+    }
+}
+
+// runs queued effects until none are left, effects that write to atoms can
+// queue further effects
+fn run_pending_effects() {
+    let already_running = with_store(|refcell_store| {
+        std::mem::replace(&mut refcell_store.borrow_mut().running_effects, true)
+    });
+    if already_running {
+        return;
+    }
+
+    // stops running effects even if one of them panics
+    struct StopEffects;
+    impl Drop for StopEffects {
+        fn drop(&mut self) {
+            with_store(|refcell_store| refcell_store.borrow_mut().running_effects = false);
+        }
+    }
+
+    let _stop_effects = StopEffects;
+    loop {
+        let effects = with_store(|refcell_store| {
+            std::mem::take(&mut refcell_store.borrow_mut().pending_effects)
+        });
+        if effects.is_empty() {
+            break;
+        }
+
+        for id in effects {
+            if let Some(effect) = clone_reactive_state_with_id::<RxFunc>(id) {
+                run_reactive_func(id, &effect);
+            }
+        }
+    }
+}
+
+/// Runs `func` inside a reactive context for `id`, so that anything it
+/// observes is recorded as a dependency of `id` and anything it stopped
+/// observing is not.
+pub fn run_in_reactive_context<F: FnOnce() -> R, R>(id: StorageKey, func: F) -> R {
+    topo::root(|| {
+        illicit::Layer::new()
+            .offer(RefCell::new(ReactiveContext::new(id)))
+            .enter(|| {
+                let result = func();
+                unlink_dead_links(id);
+                result
+            })
+    })
+}
+
+/// Runs `write` and returns whether it changed the state stored for `id`.
+///
+/// Only ids with a registered [`Memo`] are compared, any other write is
+/// considered a change.
+fn write_changes_state<F: FnOnce()>(id: StorageKey, write: F) -> bool {
+    journal_write(id);
+    let check = clone_reactive_state_with_id::<Memo>(id).map(|memo| (memo.snapshot)());
+    write();
+    check.is_none_or(|check| check())
+}
+
+/// Marks the state for `id` as memoized, writes that leave it equal to its
+/// previous value will not trigger dependent reactions.
+///
+/// Typically this is registered via `#[atom(memo)]` or `#[reaction(memo)]`.
+pub fn memoize_reactive_state_with_id<T: 'static + Clone + PartialEq>(id: StorageKey) {
+    if !reactive_state_exists_for_id::<Memo>(id) {
+        set_inert_atom_state_with_id(Memo::new::<T>(id), id);
+    }
+}
+
+/// Excludes `id` from garbage collection even when it has not been seen.
+///
+/// Typically this is registered via `#[atom(keep_alive)]`.
+pub fn keep_reactive_state_alive_with_id(id: StorageKey) {
+    with_store(|store_refcell| store_refcell.borrow_mut().kept_alive_ids.insert(id));
+}
+
+/// Rudamentary Garbage Collection for atoms and reactions
+///
+/// Copies all atom, reaction and effect ids to an unseen list. Each id is
+/// removed from it when its function is called again, i.e. `user(3)`.
+///
+/// Paired with purge_unseen_reactive_ids to remove ids that have not been
+/// seen since.
+pub fn reset_unseen_reactive_id_list() {
+    with_store(|store_refcell| {
+        let mut store_mut = store_refcell.borrow_mut();
+        let ids = store_mut
+            .id_to_key_map
+            .keys()
+            .filter(|k| matches!(k, StorageKey::SlottedKey(_)))
+            .copied()
+            .collect::<HashSet<_>>();
+        store_mut.unseen_reactive_ids = ids;
+    })
+}
+
+/// The ids that purge_unseen_reactive_ids would remove: unseen ids that are
+/// not kept alive and that no seen reaction or any effect observes.
+pub fn unseen_reactive_ids() -> Vec<StorageKey> {
+    with_store(|store_refcell| store_refcell.borrow().collectable_ids())
+}
+
+/// Removes every unseen id along with its state, reaction function and
+/// dependency edges, then resets the unseen list. Returns the removed ids.
+pub fn purge_unseen_reactive_ids() -> Vec<StorageKey> {
+    let ids = unseen_reactive_ids();
+    with_store(|store_refcell| {
+        let mut store_mut = store_refcell.borrow_mut();
+        for id in &ids {
+            store_mut.remove_id(id);
+        }
+    });
+    reset_unseen_reactive_id_list();
+    ids
+}
+
+/// Runs `func` with reaction propagation deferred until it returns.
+///
+/// Every atom set or updated inside the closure is recorded and, once the
+/// outermost batch finishes, dependent reactions are re-run in a single pass
+/// over all of them. Batches nest, and any reversible changes made inside are
+/// recorded as a single undo step.
+///
+/// ```
+/// use atomic_hooks::{atom::Atom, *};
+/// #[atom]
+/// fn a() -> Atom<i32> {
+///     0
+/// }
+/// #[atom]
+/// fn b() -> Atom<i32> {
+///     0
+/// }
+///
+/// batch(|| {
+///     a().set(1);
+///     b().set(2);
+/// });
+/// ```
+pub fn batch<F: FnOnce() -> R, R>(func: F) -> R {
+    // ends the batch even if `func` panics, the changes it made before are
+    // kept and so still propagated
+    struct EndBatch;
+    impl Drop for EndBatch {
+        fn drop(&mut self) {
+            let batched = with_store(|store_refcell| {
+                let mut store = store_refcell.borrow_mut();
+                store.batch_depth -= 1;
+                if store.batch_depth == 0 {
+                    Some((
+                        std::mem::take(&mut store.batched_ids),
+                        std::mem::take(&mut store.batch_undo_cursors),
+                    ))
+                } else {
+                    None
+                }
+            });
+
+            if let Some((ids, undo_cursors)) = batched {
+                group_batched_undo_commands(undo_cursors);
+                if std::thread::panicking() {
+                    // a reaction panicking as well is dropped, the panic of
+                    // `func` is the one that is resumed
+                    let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                        execute_reaction_nodes_for_ids(&ids)
+                    }));
+                } else {
+                    execute_reaction_nodes_for_ids(&ids);
+                }
+            }
+        }
     }
+
+    with_store(|store_refcell| store_refcell.borrow_mut().batch_depth += 1);
+    let _end_batch = EndBatch;
+    func()
 }
 
 pub fn update_atom_state_with_id<T: 'static, F: FnOnce(&mut T) -> ()>(id: StorageKey, func: F)
 where
     T: 'static,
 {
-    let mut item = remove_reactive_state_with_id::<T>(id)
-        .expect("You are trying to update a type state that doesnt exist in this context!");
-
-    func(&mut item);
-
-    set_inert_atom_state_with_id(item, id);
+    let mut allowed = true;
+    let changed = write_changes_state(id, || allowed = intercept_update(id, func));
 
     //we need to get the associated data with this key
-    execute_reaction_nodes(&id);
+    if changed && allowed {
+        execute_reaction_nodes(&id);
+    }
 }
 
 pub fn update_atom_reversible_state_with_id<T: 'static, F: FnOnce(&mut T) -> ()>(
@@ -317,31 +721,71 @@ pub fn update_atom_reversible_state_with_id<T: 'static, F: FnOnce(&mut T) -> ()>
 ) where
     T: Clone + 'static,
 {
-    let mut item = remove_reactive_state_with_id::<T>(id)
+    let previous_state = clone_reactive_state_with_id::<T>(id)
         .expect("You are trying to update a type state that doesnt exist in this context!");
+    let mut new_item = None;
+    let changed = write_changes_state(id, || {
+        if intercept_update(id, func) {
+            new_item = clone_reactive_state_with_id::<T>(id);
+        }
+    });
 
-    let previous_state = item.clone();
-    func(&mut item);
+    // vetoed updates and memoized atoms updated to an equal value have
+    // nothing to undo
+    if let (true, Some(new_item)) = (changed, new_item) {
+        record_undo_command_in(
+            undo_history_of(id),
+            crate::reverse::Command::new(
+                RxFunc::new(move || {
+                    restore_atom_state_with_id::<T>(new_item.clone(), id);
+                }),
+                RxFunc::new(move || {
+                    restore_atom_state_with_id::<T>(previous_state.clone(), id);
+                }),
+            ),
+        );
+        execute_reaction_nodes(&id);
+    }
+}
 
-    let new_item = item.clone();
-    global_reverse_queue().update(|u| {
-        u.commands.truncate(u.cursor);
+/// Applies `patch` to the reversible atom `id`, recording the patch and its
+/// inverse in its undo history rather than clones of the value.
+///
+/// The patched value goes through interceptors and write middleware like any
+/// other write, see [`intercept_patch`].
+pub fn patch_atom_reversible_state_with_id<T: Reversible + 'static>(
+    id: StorageKey,
+    patch: T::Patch,
+) {
+    let mut inverse = None;
+    let changed = write_changes_state(id, || inverse = intercept_patch::<T>(id, patch.clone()));
+
+    // vetoed patches and memoized atoms patched to an equal value have
+    // nothing to undo
+    if let (true, Some(inverse)) = (changed, inverse) {
+        record_undo_command_in(
+            undo_history_of(id),
+            crate::reverse::Command::new(
+                RxFunc::new(move || restore_patch_with_id::<T>(id, patch.clone())),
+                RxFunc::new(move || restore_patch_with_id::<T>(id, inverse.clone())),
+            ),
+        );
+        execute_reaction_nodes(&id);
+    }
+}
 
-        u.commands.push(crate::reverse::Command::new(
-            RxFunc::new(move || {
-                set_inert_atom_state_with_id::<T>(new_item.clone(), id);
-            }),
-            RxFunc::new(move || {
-                set_inert_atom_state_with_id::<T>(previous_state.clone(), id);
-            }),
-        ));
-        u.cursor += 1;
+// applies a patch for undo or redo, see `restore_atom_state_with_id`
+fn restore_patch_with_id<T: Reversible + 'static>(id: StorageKey, patch: T::Patch) {
+    let changed = write_changes_state(id, || {
+        let mut item = remove_reactive_state_with_id::<T>(id)
+            .expect("You are trying to patch a type state that doesnt exist in this context!");
+        item.apply_patch(patch);
+        set_inert_atom_state_with_id(item, id);
     });
 
-    set_inert_atom_state_with_id(item, id);
-
-    //we need to get the associated data with this key
-    execute_reaction_nodes(&id);
+    if changed {
+        execute_reaction_nodes(&id);
+    }
 }
 
 pub fn read_reactive_state_with_id<T: 'static, F: FnOnce(&T) -> R, R>(
@@ -368,6 +812,36 @@ pub fn try_read_reactive_state_with_id<T: 'static, F: FnOnce(&T) -> R, R>(
     }
 }
 
+/// Returns the key for the atom or reaction declared as `name`, called with
+/// `args`, and records `name` for it.
+///
+/// The key only depends on the name and a [`stable_hash`] of the arguments,
+/// so it is the same across builds and targets. Used by `#[atom]` and
+/// `#[reaction]`.
+///
+/// [`stable_hash`]: crate::stable_hash
+pub fn return_key_for_name_and_insert_if_required<T: 'static + Clone + Eq + Hash>(
+    name: &'static str,
+    args: T,
+) -> StorageKey {
+    let key = StorageKey::SlottedKey(SlottedKey {
+        location: stable_hash(name),
+        slot: if TypeId::of::<T>() == TypeId::of::<()>() {
+            0
+        } else {
+            stable_hash(&args)
+        },
+    });
+
+    let key = with_store(|refcell_store| {
+        refcell_store
+            .borrow_mut()
+            .return_key_for_type_and_insert_if_required(key, (name, args))
+    });
+    name_reactive_state_with_id(key, name);
+    key
+}
+
 pub fn return_key_for_type_and_insert_if_required<T: 'static + Clone + Eq + Hash>(
     value: T,
 ) -> StorageKey {
@@ -382,7 +856,7 @@ pub fn return_key_for_type_and_insert_if_required<T: 'static + Clone + Eq + Hash
         slot: 0,
     });
 
-    STORE.with(|refcell_store| {
+    with_store(|refcell_store| {
         refcell_store
             .borrow_mut()
             .return_key_for_type_and_insert_if_required(key, value.clone())
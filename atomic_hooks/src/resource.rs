@@ -0,0 +1,335 @@
+use crate::{
+    helpers::CallSite,
+    reactive_state_access::reaction::Reaction,
+    reactive_state_functions::{
+        clone_reactive_state_with_id, current_store, reactive_state_exists_for_id,
+        run_in_reactive_context, run_reactive_func, set_atom_state_with_id,
+        set_inert_atom_state_with_id, with_store,
+    },
+    store::{NodeKind, RxFunc, SlottedKey, StorageKey},
+};
+use std::{
+    cell::RefCell,
+    collections::hash_map::DefaultHasher,
+    future::Future,
+    hash::{Hash, Hasher},
+    pin::Pin,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Wake, Waker},
+};
+
+/// A future spawned by a resource.
+pub type LocalFuture = Pin<Box<dyn Future<Output = ()>>>;
+
+/// Runs the futures spawned by resources, see [`set_spawner`].
+pub type Spawner = Rc<dyn Fn(LocalFuture)>;
+
+/// The value of a [`resource`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Loadable<T, E> {
+    Loading,
+    Ready(T),
+    Error(E),
+}
+
+impl<T, E> Loadable<T, E> {
+    pub fn is_loading(&self) -> bool {
+        matches!(self, Loadable::Loading)
+    }
+
+    pub fn ready(&self) -> Option<&T> {
+        match self {
+            Loadable::Ready(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn error(&self) -> Option<&E> {
+        match self {
+            Loadable::Error(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl<T, E> From<Result<T, E>> for Loadable<T, E> {
+    fn from(result: Result<T, E>) -> Self {
+        match result {
+            Ok(value) => Loadable::Ready(value),
+            Err(error) => Loadable::Error(error),
+        }
+    }
+}
+
+/// Sets how resources in the current store spawn their futures, i.e.
+/// `wasm_bindgen_futures::spawn_local` in the browser or a
+/// [`LocalExecutor`] in tests.
+pub fn set_spawner<F: Fn(LocalFuture) + 'static>(spawner: F) {
+    with_store(|store_refcell| store_refcell.borrow_mut().spawner = Some(Rc::new(spawner)));
+}
+
+fn spawn(future: LocalFuture) {
+    let spawner = with_store(|store_refcell| store_refcell.borrow().spawner.clone())
+        .expect("No spawner found for resources, call set_spawner first");
+    spawner(future)
+}
+
+// the run of a resource whose result is still wanted
+#[derive(Clone, Copy)]
+struct ResourceGeneration(u64);
+
+/// A reaction whose value is loaded by a future.
+///
+/// `func` runs like a reaction body, anything it observes is tracked, and
+/// returns the future to await. The resource is `Loading` until the future
+/// completes. When a dependency changes it starts loading again and the
+/// result of the previous future is discarded.
+///
+/// Each call site creates a single resource, use [`resource_with_id`] for one
+/// per argument.
+///
+/// ```
+/// use atomic_hooks::{atom::Atom, *};
+/// #[atom]
+/// fn user_id() -> Atom<u32> {
+///     1
+/// }
+///
+/// let executor = LocalExecutor::new();
+/// set_spawner(executor.spawner());
+///
+/// let user_name = resource(|| {
+///     let id = user_id().observe();
+///     async move { Ok::<_, String>(format!("user {}", id)) }
+/// });
+/// assert_eq!(user_name.get(), Loadable::Loading);
+///
+/// executor.run_until_stalled();
+/// assert_eq!(user_name.get(), Loadable::Ready("user 1".to_string()));
+/// ```
+#[track_caller]
+pub fn resource<T, E, Fut, F>(func: F) -> Reaction<Loadable<T, E>>
+where
+    T: 'static,
+    E: 'static,
+    Fut: Future<Output = Result<T, E>> + 'static,
+    F: Fn() -> Fut + 'static,
+{
+    let mut hasher = DefaultHasher::new();
+    CallSite::here().hash(&mut hasher);
+    let id = StorageKey::SlottedKey(SlottedKey {
+        location: hasher.finish(),
+        slot: 0,
+    });
+    resource_with_id(id, func)
+}
+
+/// Like [`resource`], keyed to the given id.
+pub fn resource_with_id<T, E, Fut, F>(id: StorageKey, func: F) -> Reaction<Loadable<T, E>>
+where
+    T: 'static,
+    E: 'static,
+    Fut: Future<Output = Result<T, E>> + 'static,
+    F: Fn() -> Fut + 'static,
+{
+    with_store(|store_refcell| store_refcell.borrow_mut().insert_seen_id(id));
+    if !reactive_state_exists_for_id::<Loadable<T, E>>(id) {
+        with_store(|store_refcell| {
+            store_refcell
+                .borrow_mut()
+                .set_state_with_id(NodeKind::Reaction, &id)
+        });
+
+        let load = RxFunc::new(move || {
+            let future = run_in_reactive_context(id, &func);
+            let generation = clone_reactive_state_with_id::<ResourceGeneration>(id)
+                .map_or(0, |generation| generation.0 + 1);
+            set_inert_atom_state_with_id(ResourceGeneration(generation), id);
+            set_inert_atom_state_with_id::<Loadable<T, E>>(Loadable::Loading, id);
+
+            let store = current_store();
+            spawn(Box::pin(async move {
+                let result = future.await;
+                store.enter(|| {
+                    let current = clone_reactive_state_with_id::<ResourceGeneration>(id);
+                    if current.map(|current| current.0) == Some(generation) {
+                        set_atom_state_with_id::<Loadable<T, E>>(result.into(), id);
+                    }
+                })
+            }));
+        });
+
+        with_store(|store_refcell| store_refcell.borrow_mut().new_reaction(&id, load.clone()));
+        run_reactive_func(id, &load);
+    }
+    Reaction::new(id)
+}
+
+/// A single threaded executor, for running resources outside the browser.
+#[derive(Clone)]
+pub struct LocalExecutor {
+    tasks: Rc<RefCell<Vec<LocalFuture>>>,
+    woken: Arc<WakeFlag>,
+}
+
+struct WakeFlag(AtomicBool);
+
+impl Wake for WakeFlag {
+    fn wake(self: Arc<Self>) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+impl LocalExecutor {
+    pub fn new() -> LocalExecutor {
+        LocalExecutor {
+            tasks: Rc::new(RefCell::new(vec![])),
+            woken: Arc::new(WakeFlag(AtomicBool::new(false))),
+        }
+    }
+
+    /// A spawner that queues futures on this executor, for [`set_spawner`].
+    pub fn spawner(&self) -> impl Fn(LocalFuture) + 'static {
+        let executor = self.clone();
+        move |future| {
+            executor.tasks.borrow_mut().push(future);
+            executor.woken.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// Polls every task until none of them can make progress.
+    pub fn run_until_stalled(&self) {
+        let waker = Waker::from(self.woken.clone());
+        let mut context = Context::from_waker(&waker);
+
+        while self.woken.0.swap(false, Ordering::SeqCst) {
+            let tasks = std::mem::take(&mut *self.tasks.borrow_mut());
+            let pending = tasks
+                .into_iter()
+                .filter_map(|mut task| match task.as_mut().poll(&mut context) {
+                    Poll::Ready(()) => None,
+                    Poll::Pending => Some(task),
+                })
+                .collect::<Vec<_>>();
+            self.tasks.borrow_mut().extend(pending);
+        }
+    }
+
+    /// The number of tasks that have not completed yet.
+    pub fn pending(&self) -> usize {
+        self.tasks.borrow().len()
+    }
+}
+
+impl Default for LocalExecutor {
+    fn default() -> Self {
+        LocalExecutor::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{reactive_state_access::atom::Atom, *};
+    use std::collections::HashMap;
+
+    #[atom]
+    fn search_query() -> Atom<u32> {
+        1
+    }
+
+    // a future that completes once it is opened
+    #[derive(Clone, Default)]
+    struct Gate(Rc<RefCell<GateState>>);
+
+    #[derive(Default)]
+    struct GateState {
+        result: Option<Result<u32, String>>,
+        waker: Option<Waker>,
+    }
+
+    impl Gate {
+        fn open(&self, result: Result<u32, String>) {
+            let mut gate = self.0.borrow_mut();
+            gate.result = Some(result);
+            if let Some(waker) = gate.waker.take() {
+                waker.wake();
+            }
+        }
+
+        fn wait(&self) -> impl Future<Output = Result<u32, String>> {
+            let gate = self.clone();
+            std::future::poll_fn(move |context| {
+                let mut gate = gate.0.borrow_mut();
+                match gate.result.take() {
+                    Some(result) => Poll::Ready(result),
+                    None => {
+                        gate.waker = Some(context.waker().clone());
+                        Poll::Pending
+                    }
+                }
+            })
+        }
+    }
+
+    fn search(gates: &Rc<RefCell<HashMap<u32, Gate>>>) -> Reaction<Loadable<u32, String>> {
+        let gates = gates.clone();
+        resource(move || {
+            let query = search_query().observe();
+            let gate = gates.borrow_mut().entry(query).or_default().clone();
+            async move { gate.wait().await.map(|hits| hits * query) }
+        })
+    }
+
+    #[test]
+    fn test_resource_discards_stale_results() {
+        let executor = LocalExecutor::new();
+        set_spawner(executor.spawner());
+        let gates = Rc::new(RefCell::new(HashMap::new()));
+
+        let results = search(&gates);
+        assert_eq!(results.get(), Loadable::Loading);
+
+        search_query().set(2);
+        gates.borrow()[&1].open(Ok(10));
+        executor.run_until_stalled();
+        assert_eq!(results.get(), Loadable::Loading, "query 1 is stale");
+
+        gates.borrow()[&2].open(Ok(10));
+        executor.run_until_stalled();
+        assert_eq!(results.get(), Loadable::Ready(20));
+        assert_eq!(executor.pending(), 0);
+    }
+
+    #[test]
+    fn test_resource_error() {
+        let executor = LocalExecutor::new();
+        set_spawner(executor.spawner());
+        let gates = Rc::new(RefCell::new(HashMap::new()));
+
+        let results = search(&gates);
+        gates.borrow()[&1].open(Err("offline".to_string()));
+        executor.run_until_stalled();
+        assert_eq!(results.get().error(), Some(&"offline".to_string()));
+    }
+
+    #[test]
+    fn test_resource_is_kept_while_used() {
+        let executor = LocalExecutor::new();
+        set_spawner(executor.spawner());
+        let gates = Rc::new(RefCell::new(HashMap::new()));
+
+        let results = search(&gates);
+        reset_unseen_reactive_id_list();
+        search(&gates);
+        assert!(!purge_unseen_reactive_ids().contains(&results.id));
+
+        gates.borrow()[&1].open(Ok(3));
+        executor.run_until_stalled();
+        assert_eq!(results.get(), Loadable::Ready(3));
+    }
+}
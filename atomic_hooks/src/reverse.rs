@@ -1,17 +1,80 @@
-use crate::{atom::Atom, *};
+use crate::{atom::Atom, middleware::skip_write_middleware_with_id, *};
 
-use store::RxFunc;
+use std::rc::Rc;
+use store::{RxFunc, StorageKey};
 
+/// The undo history of reversible atoms.
+///
+/// Making a change after undoing discards the undone commands, so the
+/// history is always a single line.
 #[derive(Default, Clone)]
 pub struct UndoStore {
     pub commands: Vec<Command>,
     pub cursor: usize,
+    pub max_depth: Option<usize>,
+}
+
+impl UndoStore {
+    /// Records `command` as the latest change, discarding any undone
+    /// commands.
+    pub fn push(&mut self, command: Command) {
+        self.commands.truncate(self.cursor);
+        self.commands.push(command);
+        self.cursor += 1;
+    }
+
+    /// Collapses every command between `cursor` and the current cursor into a
+    /// single command, so that they are undone and redone together.
+    pub fn group_since(&mut self, cursor: usize) {
+        if self.cursor <= cursor + 1 {
+            return;
+        }
+        let commands = self.commands.drain(cursor..self.cursor).collect();
+        self.commands.insert(cursor, Command::group(commands));
+        self.cursor = cursor + 1;
+    }
+
+    /// Groups every command since `cursor` and labels the result.
+    pub fn label_since(&mut self, cursor: usize, label: &str) {
+        self.group_since(cursor);
+        if self.cursor == cursor + 1 {
+            self.commands[cursor].label = Some(label.into());
+        }
+    }
+
+    /// Removes the command at `index`.
+    pub fn remove(&mut self, index: usize) -> Command {
+        if index < self.cursor {
+            self.cursor -= 1;
+        }
+        self.commands.remove(index)
+    }
+
+    /// Inserts `command` at the cursor, as the latest applied command if
+    /// `applied` or else as the next one to redo.
+    pub fn insert(&mut self, command: Command, applied: bool) {
+        self.commands.insert(self.cursor, command);
+        if applied {
+            self.cursor += 1;
+        }
+        self.truncate_to_max_depth();
+    }
+
+    /// Drops the oldest commands beyond `max_depth`.
+    pub fn truncate_to_max_depth(&mut self) {
+        if let Some(max_depth) = self.max_depth {
+            let excess = self.commands.len().saturating_sub(max_depth);
+            self.commands.drain(..excess);
+            self.cursor = self.cursor.saturating_sub(excess);
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct Command {
     do_cmd: RxFunc,
     reverse_cmd: RxFunc,
+    pub label: Option<Rc<str>>,
 }
 
 impl Command {
@@ -19,19 +82,308 @@ impl Command {
         Self {
             do_cmd,
             reverse_cmd: undo_cmd,
+            label: None,
+        }
+    }
+
+    /// Combines several commands into one. The grouped command redoes them
+    /// in order and undoes them in reverse order, each inside a single
+    /// [`batch`](crate::batch). It takes the first label among them.
+    pub fn group(commands: Vec<Command>) -> Self {
+        let label = commands.iter().find_map(|command| command.label.clone());
+        let do_commands = Rc::new(commands);
+        let reverse_commands = do_commands.clone();
+        Self {
+            do_cmd: RxFunc::new(move || {
+                batch(|| {
+                    for command in do_commands.iter() {
+                        (command.do_cmd.func)();
+                    }
+                })
+            }),
+            reverse_cmd: RxFunc::new(move || {
+                batch(|| {
+                    for command in reverse_commands.iter().rev() {
+                        (command.reverse_cmd.func)();
+                    }
+                })
+            }),
+            label,
         }
     }
 }
 
-#[atom]
-pub fn global_reverse_queue() -> Atom<UndoStore> {
+/// A value that can be changed by patches, so that the undo history of a
+/// [`ReversibleAtom`](crate::reversible_atom::ReversibleAtom) holding it can
+/// record the patches made with
+/// [`ReversibleAtom::patch`](crate::reversible_atom::ReversibleAtom::patch)
+/// rather than clones of the whole value.
+///
+/// ```
+/// use atomic_hooks::{reversible_atom::ReversibleAtom, *};
+/// #[derive(Clone, Default)]
+/// struct Document {
+///     nodes: Vec<String>,
+/// }
+///
+/// #[derive(Clone)]
+/// enum DocumentPatch {
+///     Insert(usize, String),
+///     Remove(usize),
+/// }
+///
+/// impl Reversible for Document {
+///     type Patch = DocumentPatch;
+///
+///     fn apply_patch(&mut self, patch: DocumentPatch) -> DocumentPatch {
+///         match patch {
+///             DocumentPatch::Insert(index, node) => {
+///                 self.nodes.insert(index, node);
+///                 DocumentPatch::Remove(index)
+///             }
+///             DocumentPatch::Remove(index) => {
+///                 DocumentPatch::Insert(index, self.nodes.remove(index))
+///             }
+///         }
+///     }
+/// }
+///
+/// #[atom(reversible)]
+/// fn document() -> ReversibleAtom<Document> {
+///     Document::default()
+/// }
+///
+/// document().patch(DocumentPatch::Insert(0, "title".to_string()));
+/// global_reverse_queue().travel_backwards();
+/// assert!(document().get().nodes.is_empty());
+/// global_reverse_queue().travel_forwards();
+/// assert_eq!(document().get().nodes, vec!["title".to_string()]);
+/// ```
+pub trait Reversible {
+    type Patch: Clone + 'static;
+
+    /// Applies `patch` and returns the patch that reverts it.
+    fn apply_patch(&mut self, patch: Self::Patch) -> Self::Patch;
+}
+
+/// An entry of [`GlobalUndo::history`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub label: Option<String>,
+    /// Whether the entry is applied, i.e. can be undone rather than redone.
+    pub applied: bool,
+}
+
+#[atom(keep_alive)]
+fn global_undo_history() -> Atom<UndoStore> {
+    UndoStore::default()
+}
+
+#[atom(keep_alive)]
+fn named_undo_history(name: &'static str) -> Atom<UndoStore> {
     UndoStore::default()
 }
+
+// undo histories update themselves on every reversible change, which is not
+// of interest to write middleware
+fn undo_history(history: Atom<UndoStore>) -> Atom<UndoStore> {
+    skip_write_middleware_with_id(history.id);
+    history
+}
+
+/// The default undo history, used by `#[atom(reversible)]`.
+pub fn global_reverse_queue() -> Atom<UndoStore> {
+    undo_history(global_undo_history())
+}
+
+/// The undo history named `name`, used by `#[atom(reversible = "name")]`.
+pub fn undo_scope(name: &'static str) -> Atom<UndoStore> {
+    undo_history(named_undo_history(name))
+}
+
+// the undo scope of a reversible atom, if it is not the global one
+struct UndoScopeName(&'static str);
+
+/// Records the changes of the reversible atom `id` in the undo scope `name`,
+/// the history returned by `undo_scope(name)`. Each scope is undone and
+/// redone independently of the others and of `global_reverse_queue()`.
+///
+/// Typically this is used via `#[atom(reversible = "...")]`.
+///
+/// ```
+/// use atomic_hooks::{reversible_atom::ReversibleAtom, *};
+/// #[atom(reversible = "canvas")]
+/// fn zoom() -> ReversibleAtom<u32> {
+///     100
+/// }
+///
+/// #[atom(reversible = "sidebar")]
+/// fn sidebar_width() -> ReversibleAtom<u32> {
+///     200
+/// }
+///
+/// zoom().set(150);
+/// sidebar_width().set(300);
+///
+/// undo_scope("canvas").travel_backwards();
+/// assert_eq!(zoom().get(), 100);
+/// assert_eq!(sidebar_width().get(), 300);
+/// assert!(!global_reverse_queue().can_undo());
+/// ```
+pub fn set_undo_scope(id: StorageKey, name: &'static str) {
+    set_inert_atom_state_with_id(UndoScopeName(name), id);
+}
+
+/// The undo history the changes of the reversible atom `id` are recorded in.
+pub fn undo_history_of(id: StorageKey) -> Atom<UndoStore> {
+    let scope = with_store(|store_refcell| {
+        store_refcell
+            .borrow()
+            .get_state_with_id::<UndoScopeName>(&id)
+            .map(|scope| scope.0)
+    });
+    match scope {
+        Some(name) => undo_scope(name),
+        None => global_reverse_queue(),
+    }
+}
+
+/// Records `command` in the global undo history.
+pub fn record_undo_command(command: Command) {
+    record_undo_command_in(global_reverse_queue(), command)
+}
+
+/// Records `command` in the undo history `queue`.
+pub fn record_undo_command_in(queue: Atom<UndoStore>, command: Command) {
+    let cursor = queue.get_with(|queue| queue.cursor);
+    let in_batch = with_store(|store_refcell| {
+        let mut store = store_refcell.borrow_mut();
+        if store.batch_depth == 0 {
+            return false;
+        }
+        if !store
+            .batch_undo_cursors
+            .iter()
+            .any(|(id, _)| *id == queue.id)
+        {
+            store.batch_undo_cursors.push((queue.id, cursor));
+        }
+        true
+    });
+    queue.update(|queue| {
+        queue.push(command);
+        // batches group their commands by position, so they truncate at the end
+        if !in_batch {
+            queue.truncate_to_max_depth();
+        }
+    });
+}
+
+// groups the commands recorded in each history by the batch that just ended
+pub(crate) fn group_batched_undo_commands(undo_cursors: Vec<(StorageKey, usize)>) {
+    for (id, cursor) in undo_cursors {
+        let queue = Atom::<UndoStore>::new(id);
+        if queue.get_with(|queue| queue.cursor > cursor + 1 || queue.max_depth.is_some()) {
+            queue.update(|queue| {
+                queue.group_since(cursor);
+                queue.truncate_to_max_depth();
+            });
+        }
+    }
+}
+
+/// Runs `func` and records every reversible change it makes as a single
+/// labelled entry of each undo history it changes.
+///
+/// ```
+/// use atomic_hooks::{reversible_atom::ReversibleAtom, *};
+/// #[atom(reversible)]
+/// fn layer_name() -> ReversibleAtom<String> {
+///     "Layer 1".to_string()
+/// }
+///
+/// undoable("Rename layer", || layer_name().set("Background".to_string()));
+/// assert!(global_reverse_queue().can_undo());
+/// assert_eq!(
+///     global_reverse_queue().history()[0].label.as_deref(),
+///     Some("Rename layer")
+/// );
+///
+/// global_reverse_queue().travel_backwards();
+/// assert_eq!(layer_name().get(), "Layer 1");
+/// ```
+pub fn undoable<F: FnOnce() -> R, R>(label: &str, func: F) -> R {
+    batch(|| {
+        let cursors = undo_cursors_in_batch();
+        let result = func();
+        for (id, cursor) in undo_cursors_since(cursors) {
+            Atom::<UndoStore>::new(id).update(|queue| queue.label_since(cursor, label));
+        }
+        result
+    })
+}
+
+// the current cursor of each history changed in the current batch
+pub(crate) fn undo_cursors_in_batch() -> Vec<(StorageKey, usize)> {
+    with_store(|store_refcell| store_refcell.borrow().batch_undo_cursors.clone())
+        .into_iter()
+        .map(|(id, _)| {
+            (
+                id,
+                Atom::<UndoStore>::new(id).get_with(|queue| queue.cursor),
+            )
+        })
+        .collect()
+}
+
+// the cursor of each history changed in the current batch since `cursors` was
+// taken with `undo_cursors_in_batch`, before its first change since then
+pub(crate) fn undo_cursors_since(cursors: Vec<(StorageKey, usize)>) -> Vec<(StorageKey, usize)> {
+    with_store(|store_refcell| store_refcell.borrow().batch_undo_cursors.clone())
+        .into_iter()
+        .map(|(id, batch_cursor)| {
+            let cursor = cursors
+                .iter()
+                .find(|(cursor_id, _)| *cursor_id == id)
+                .map_or(batch_cursor, |(_, cursor)| *cursor);
+            (id, cursor)
+        })
+        .collect()
+}
+
+// drops the commands recorded in each history since `cursors` was taken
+pub(crate) fn discard_undo_commands_since(cursors: Vec<(StorageKey, usize)>) {
+    for (id, cursor) in undo_cursors_since(cursors) {
+        Atom::<UndoStore>::new(id).update(|queue| {
+            queue.commands.truncate(cursor);
+            queue.cursor = queue.cursor.min(cursor);
+        });
+    }
+}
+
+/// The API of an undo history, either `global_reverse_queue()` or an
+/// `undo_scope(name)`.
 pub trait GlobalUndo {
     fn travel_backwards(&self);
     fn travel_forwards(&self);
     fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
     fn travel_to_cursor(&self, cursor: usize);
+    /// The number of applied entries.
+    fn cursor(&self) -> usize;
+    fn can_undo(&self) -> bool;
+    fn can_redo(&self) -> bool;
+    /// Every entry, oldest first.
+    fn history(&self) -> Vec<HistoryEntry>;
+    /// Limits the history to `max_depth` entries, dropping the oldest ones.
+    fn set_max_depth(&self, max_depth: Option<usize>);
+    /// Discards every entry, leaving the atoms as they are.
+    fn clear(&self);
+    /// Moves the entry at `index` to the cursor of `history`, keeping whether
+    /// it is applied.
+    fn move_entry(&self, index: usize, history: Atom<UndoStore>);
 }
 
 impl GlobalUndo for Atom<UndoStore> {
@@ -40,43 +392,278 @@ impl GlobalUndo for Atom<UndoStore> {
     }
 
     fn travel_to_cursor(&self, cursor: usize) {
-        assert!(cursor > 0);
-        assert!(cursor < self.len());
-
-        update_atom_state_with_id::<UndoStore, _>(self.id, |queue| {
-            if cursor > queue.cursor {
-                while cursor < queue.cursor {
-                    if queue.cursor > 0 {
-                        (queue.commands[queue.cursor - 1].reverse_cmd.func)();
-                        queue.cursor -= 1;
-                    }
-                }
-            } else if cursor < queue.cursor {
-                while cursor < queue.cursor {
-                    if queue.cursor < queue.commands.len() {
-                        (queue.commands[queue.cursor].do_cmd.func)();
-                        queue.cursor += 1;
-                    }
-                }
+        assert!(cursor <= self.len());
+
+        batch(|| {
+            while self.cursor() > cursor {
+                self.travel_backwards();
+            }
+            while self.cursor() < cursor {
+                self.travel_forwards();
             }
         })
     }
 
+    // commands run after the queue is updated, as they may record changes
+    // themselves, and propagate once
     fn travel_backwards(&self) {
-        update_atom_state_with_id::<UndoStore, _>(self.id, |queue| {
-            if queue.cursor > 0 {
-                (queue.commands[queue.cursor - 1].reverse_cmd.func)();
-                queue.cursor -= 1;
-            }
+        let command = self.get_with(|queue| {
+            queue
+                .cursor
+                .checked_sub(1)
+                .map(|index| queue.commands[index].clone())
         });
+        if let Some(command) = command {
+            self.update(|queue| queue.cursor -= 1);
+            batch(|| (command.reverse_cmd.func)());
+        }
     }
 
     fn travel_forwards(&self) {
-        update_atom_state_with_id::<UndoStore, _>(self.id, |queue| {
-            if queue.cursor < queue.commands.len() {
-                (queue.commands[queue.cursor].do_cmd.func)();
-                queue.cursor += 1;
+        let command = self.get_with(|queue| queue.commands.get(queue.cursor).cloned());
+        if let Some(command) = command {
+            self.update(|queue| queue.cursor += 1);
+            batch(|| (command.do_cmd.func)());
+        }
+    }
+
+    fn cursor(&self) -> usize {
+        self.get_with(|queue| queue.cursor)
+    }
+
+    fn can_undo(&self) -> bool {
+        self.cursor() > 0
+    }
+
+    fn can_redo(&self) -> bool {
+        self.get_with(|queue| queue.cursor < queue.commands.len())
+    }
+
+    fn history(&self) -> Vec<HistoryEntry> {
+        self.get_with(|queue| {
+            queue
+                .commands
+                .iter()
+                .enumerate()
+                .map(|(index, command)| HistoryEntry {
+                    label: command.label.as_deref().map(str::to_string),
+                    applied: index < queue.cursor,
+                })
+                .collect()
+        })
+    }
+
+    fn set_max_depth(&self, max_depth: Option<usize>) {
+        self.update(|queue| {
+            queue.max_depth = max_depth;
+            queue.truncate_to_max_depth();
+        })
+    }
+
+    fn clear(&self) {
+        self.update(|queue| {
+            queue.commands.clear();
+            queue.cursor = 0;
+        })
+    }
+
+    fn move_entry(&self, index: usize, history: Atom<UndoStore>) {
+        assert!(index < self.len());
+        if history.id == self.id {
+            return;
+        }
+
+        let mut applied = false;
+        let mut command = None;
+        self.update(|queue| {
+            applied = index < queue.cursor;
+            command = Some(queue.remove(index));
+        });
+        if let Some(command) = command {
+            history.update(|queue| queue.insert(command, applied));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::reactive_state_access::{reaction::Reaction, reversible_atom::ReversibleAtom};
+
+    #[atom(reversible)]
+    fn brush_size() -> ReversibleAtom<u32> {
+        1
+    }
+
+    #[atom(reversible = "canvas")]
+    fn canvas_zoom() -> ReversibleAtom<u32> {
+        100
+    }
+
+    #[atom(reversible = "panel")]
+    fn panel_width() -> ReversibleAtom<u32> {
+        200
+    }
+
+    thread_local! {
+        static LAYER_CLONES: std::cell::Cell<u32> = const { std::cell::Cell::new(0) };
+    }
+
+    #[derive(Default)]
+    struct Layers(Vec<u32>);
+
+    impl Clone for Layers {
+        fn clone(&self) -> Self {
+            LAYER_CLONES.with(|clones| clones.set(clones.get() + 1));
+            Layers(self.0.clone())
+        }
+    }
+
+    impl Reversible for Layers {
+        // the new layer to push, or None to pop the last one
+        type Patch = Option<u32>;
+
+        fn apply_patch(&mut self, patch: Option<u32>) -> Option<u32> {
+            match patch {
+                Some(layer) => {
+                    self.0.push(layer);
+                    None
+                }
+                None => self.0.pop(),
             }
+        }
+    }
+
+    #[atom(reversible)]
+    fn layers() -> ReversibleAtom<Layers> {
+        Layers::default()
+    }
+
+    #[reaction]
+    fn brush_label() -> Reaction<String> {
+        format!("{}px", brush_size().observe())
+    }
+
+    #[test]
+    fn test_history_labels_and_truncation() {
+        let history = global_reverse_queue();
+        assert!(!history.can_undo());
+        assert!(history.is_empty());
+
+        undoable("Resize brush", || {
+            brush_size().set(2);
+            brush_size().set(3);
+        });
+        brush_size().set(4);
+        assert_eq!(
+            history.history(),
+            vec![
+                HistoryEntry {
+                    label: Some("Resize brush".to_string()),
+                    applied: true,
+                },
+                HistoryEntry {
+                    label: None,
+                    applied: true,
+                },
+            ]
+        );
+
+        history.travel_backwards();
+        assert!(history.can_redo());
+        brush_size().set(5);
+        assert!(!history.can_redo(), "editing after undo drops the redo");
+        assert_eq!(history.len(), 2);
+
+        history.travel_to_cursor(0);
+        assert_eq!(brush_size().get(), 1);
+        history.travel_to_cursor(2);
+        assert_eq!(brush_size().get(), 5);
+    }
+
+    #[test]
+    fn test_undo_propagates() {
+        let label = brush_label();
+        brush_size().set(8);
+        assert_eq!(label.get(), "8px");
+
+        global_reverse_queue().travel_backwards();
+        assert_eq!(label.get(), "1px");
+        global_reverse_queue().travel_forwards();
+        assert_eq!(label.get(), "8px");
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let history = global_reverse_queue();
+        history.set_max_depth(Some(2));
+        for size in 2..6 {
+            brush_size().set(size);
+        }
+        batch(|| {
+            brush_size().set(10);
+            brush_size().set(11);
+            brush_size().set(12);
+        });
+        assert_eq!(history.len(), 2);
+
+        history.travel_backwards();
+        assert_eq!(brush_size().get(), 5, "the batch is a single entry");
+        history.travel_backwards();
+        history.travel_backwards();
+        assert_eq!(brush_size().get(), 4, "older entries were dropped");
+    }
+
+    #[test]
+    fn test_undo_scopes() {
+        let canvas = undo_scope("canvas");
+        let panel = undo_scope("panel");
+        canvas_zoom().set(150);
+        panel_width().set(300);
+        brush_size().set(2);
+        undoable("Resize", || {
+            canvas_zoom().set(200);
+            panel_width().set(400);
         });
+        assert_eq!((canvas.len(), panel.len()), (2, 2));
+        assert_eq!(
+            panel.history()[1].label.as_deref(),
+            Some("Resize"),
+            "undoable labels every scope it changes"
+        );
+
+        canvas.travel_backwards();
+        canvas.travel_backwards();
+        assert_eq!(canvas_zoom().get(), 100);
+        assert_eq!(panel_width().get(), 400, "other scopes are untouched");
+        assert_eq!(brush_size().get(), 2);
+
+        panel.move_entry(1, canvas);
+        assert_eq!((canvas.len(), panel.len()), (3, 1));
+        canvas.travel_backwards();
+        assert_eq!(panel_width().get(), 300);
+        assert_eq!(canvas_zoom().get(), 100);
+
+        panel.clear();
+        assert!(!panel.can_undo());
+        assert!(global_reverse_queue().can_undo());
+        assert_eq!(panel_width().get(), 300);
+    }
+
+    #[test]
+    fn test_patches_do_not_clone() {
+        layers().patch(Some(1));
+        layers().patch(Some(2));
+        layers().patch(None);
+        assert_eq!(layers().get_with(|layers| layers.0.clone()), vec![1]);
+
+        let history = global_reverse_queue();
+        history.travel_backwards();
+        assert_eq!(layers().get_with(|layers| layers.0.clone()), vec![1, 2]);
+        history.travel_to_cursor(0);
+        assert!(layers().get_with(|layers| layers.0.is_empty()));
+        history.travel_to_cursor(3);
+        assert_eq!(layers().get_with(|layers| layers.0.clone()), vec![1]);
+        assert_eq!(LAYER_CLONES.with(std::cell::Cell::get), 0);
     }
 }
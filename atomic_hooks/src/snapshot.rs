@@ -0,0 +1,212 @@
+use crate::{
+    middleware::register_clone_type,
+    reactive_state_functions::{
+        batch, clone_reactive_state_with_id, reactive_state_exists_for_id,
+        restore_atom_state_with_id, set_inert_atom_state_with_id, with_store,
+    },
+    store::StorageKey,
+};
+use std::{marker::PhantomData, rc::Rc};
+
+/// The value of every atom at the time [`snapshot`] was called. Atoms are
+/// only included once they have been created.
+///
+/// ```
+/// use atomic_hooks::{atom::Atom, *};
+/// #[atom]
+/// fn step() -> Atom<u32> {
+///     1
+/// }
+///
+/// step();
+/// let before_wizard = snapshot();
+/// step().set(3);
+/// restore(&before_wizard);
+/// assert_eq!(step().get(), 1);
+/// ```
+#[derive(Clone, Default)]
+pub struct Snapshot {
+    values: Vec<(StorageKey, Restore)>,
+}
+
+impl Snapshot {
+    /// The number of atoms captured.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    // captures the current value of the atom `id`, unless it already is
+    pub(crate) fn capture(&mut self, id: StorageKey) {
+        if self.values.iter().any(|(captured, _)| *captured == id) {
+            return;
+        }
+        if let Some(snapshotter) = clone_reactive_state_with_id::<Snapshotter>(id) {
+            if let Some(restore) = (snapshotter.0)() {
+                self.values.push((id, restore));
+            }
+        }
+    }
+
+    // adds the values of atoms in `other` that are not captured yet
+    pub(crate) fn merge(&mut self, other: Snapshot) {
+        for (id, restore) in other.values {
+            if !self.values.iter().any(|(captured, _)| *captured == id) {
+                self.values.push((id, restore));
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Snapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.values.iter().map(|(id, _)| id))
+            .finish()
+    }
+}
+
+// writes a captured value back to its atom
+type Restore = Rc<dyn Fn()>;
+
+// clones the current value of an atom into a closure that writes it back
+#[derive(Clone)]
+struct Snapshotter(Rc<dyn Fn() -> Option<Restore>>);
+
+/// Captures the current value of every atom whose type is `Clone`.
+pub fn snapshot() -> Snapshot {
+    let snapshotters = with_store(|store_refcell| {
+        let store = store_refcell.borrow();
+        store
+            .get_secondarymap::<Snapshotter>()
+            .map(|map| {
+                map.iter()
+                    .filter_map(|(key, snapshotter)| {
+                        Some((*store.primary_slotmap.get(key)?, snapshotter.clone()))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default()
+    });
+
+    Snapshot {
+        values: snapshotters
+            .into_iter()
+            .filter_map(|(id, snapshotter)| Some((id, (snapshotter.0)()?)))
+            .collect(),
+    }
+}
+
+/// Writes every atom captured in `snapshot` back and re-runs dependent
+/// reactions in a single pass. Atoms created after the snapshot was taken are
+/// left as they are. Restored values are not seen by write middleware.
+pub fn restore(snapshot: &Snapshot) {
+    batch(|| {
+        for (_, restore) in &snapshot.values {
+            restore();
+        }
+    })
+}
+
+/// Registers an atom with [`snapshot`], for use by `#[atom]`.
+///
+/// Atoms of a type that is not `Clone` are skipped through the
+/// [`SkipSnapshot`] fallback.
+pub struct SnapshotRegistrar<T> {
+    id: StorageKey,
+    _phantom_data_stored_type: PhantomData<T>,
+}
+
+impl<T> SnapshotRegistrar<T> {
+    pub fn new(id: StorageKey) -> Self {
+        SnapshotRegistrar {
+            id,
+            _phantom_data_stored_type: PhantomData,
+        }
+    }
+}
+
+pub trait RegisterSnapshot {
+    fn register_snapshot(&self);
+}
+
+impl<T: 'static + Clone> RegisterSnapshot for SnapshotRegistrar<T> {
+    fn register_snapshot(&self) {
+        let id = self.id;
+        if reactive_state_exists_for_id::<Snapshotter>(id) {
+            return;
+        }
+        // middleware can veto updates of types it can clone
+        register_clone_type::<T>();
+
+        let snapshotter = Snapshotter(Rc::new(move || {
+            let value = clone_reactive_state_with_id::<T>(id)?;
+            Some(Rc::new(move || {
+                if reactive_state_exists_for_id::<T>(id) {
+                    restore_atom_state_with_id::<T>(value.clone(), id)
+                }
+            }) as Restore)
+        }));
+        set_inert_atom_state_with_id(snapshotter, id);
+    }
+}
+
+pub trait SkipSnapshot {
+    fn register_snapshot(&self);
+}
+
+impl<T> SkipSnapshot for &SnapshotRegistrar<T> {
+    fn register_snapshot(&self) {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        reactive_state_access::{atom::Atom, reaction::Reaction},
+        *,
+    };
+
+    #[atom]
+    fn wizard_step() -> Atom<u32> {
+        1
+    }
+
+    #[atom]
+    fn wizard_answers() -> Atom<Vec<String>> {
+        vec![]
+    }
+
+    #[reaction]
+    fn wizard_summary() -> Reaction<String> {
+        format!(
+            "step {}: {}",
+            wizard_step().observe(),
+            wizard_answers().observe().join(", ")
+        )
+    }
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let summary = wizard_summary();
+        let before_wizard = snapshot();
+
+        wizard_step().set(2);
+        wizard_answers().update(|answers| answers.push("yes".to_string()));
+        assert_eq!(summary.get(), "step 2: yes");
+
+        let changes = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let subscribed_changes = changes.clone();
+        let _subscription = summary.subscribe(move |_, new| {
+            subscribed_changes.borrow_mut().push(new.clone());
+        });
+
+        restore(&before_wizard);
+        assert_eq!(wizard_step().get(), 1);
+        assert_eq!(wizard_answers().get(), Vec::<String>::new());
+        assert_eq!(*changes.borrow(), vec!["step 1: "], "reactions re-run once");
+    }
+}
@@ -0,0 +1,231 @@
+use crate::{
+    reactive_state_functions::{
+        clone_reactive_state_with_id, reactive_state_exists_for_id, set_inert_atom_state_with_id,
+        with_store,
+    },
+    store::StorageKey,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    rc::Rc,
+};
+
+/// A 64 bit FNV-1a hasher that gives the same result on every target, so
+/// that keys computed on the server match the ones computed in the browser.
+///
+/// `usize` and `isize` are always hashed as 64 bit values.
+#[derive(Clone, Copy, Debug)]
+pub struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        StableHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_i64(i as i64);
+    }
+}
+
+/// Hashes `value` with a [`StableHasher`].
+pub fn stable_hash<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = StableHasher::default();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+// serializes an atom under its stable key
+#[derive(Clone)]
+struct Dehydrator {
+    key: Rc<str>,
+    serialize: Rc<dyn Fn() -> Option<serde_json::Value>>,
+}
+
+/// Serializes every atom whose type is `Serialize + Deserialize` into a
+/// JSON object, keyed by the atom's debug name, its function path and a hash
+/// of its arguments. Embed it in the server rendered page and pass it to [`hydrate`]
+/// in the browser.
+///
+/// Topological `use_state` values are not included.
+pub fn dehydrate() -> String {
+    let dehydrators = with_store(|store_refcell| {
+        store_refcell
+            .borrow()
+            .get_secondarymap::<Dehydrator>()
+            .map(|map| map.values().cloned().collect::<Vec<_>>())
+            .unwrap_or_default()
+    });
+
+    let values = dehydrators
+        .into_iter()
+        .filter_map(|dehydrator| Some((dehydrator.key.to_string(), (dehydrator.serialize)()?)))
+        .collect::<serde_json::Map<_, _>>();
+    serde_json::Value::Object(values).to_string()
+}
+
+/// Loads state produced by [`dehydrate`]. Each atom takes its value from it
+/// when it is first created, instead of running its initializer.
+///
+/// ```
+/// use atomic_hooks::{atom::Atom, *};
+/// #[atom]
+/// fn greeting() -> Atom<String> {
+///     "Hello".to_string()
+/// }
+///
+/// let server = StoreHandle::new();
+/// let state = server.enter(|| {
+///     greeting().set("Hello from the server".to_string());
+///     dehydrate()
+/// });
+///
+/// hydrate(&state).unwrap();
+/// assert_eq!(greeting().get(), "Hello from the server");
+/// ```
+pub fn hydrate(json: &str) -> Result<(), serde_json::Error> {
+    let values = serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(json)?;
+    with_store(|store_refcell| store_refcell.borrow_mut().hydrated_values.extend(values));
+    Ok(())
+}
+
+/// Registers an atom with [`dehydrate`] and [`hydrate`], for use by
+/// `#[atom]`.
+///
+/// Atoms of a type that cannot be serialized are skipped through the
+/// [`SkipHydrate`] fallback.
+pub struct HydrateRegistrar<T> {
+    id: StorageKey,
+    _phantom_data_stored_type: PhantomData<T>,
+}
+
+impl<T> HydrateRegistrar<T> {
+    pub fn new(id: StorageKey) -> Self {
+        HydrateRegistrar {
+            id,
+            _phantom_data_stored_type: PhantomData,
+        }
+    }
+}
+
+pub trait RegisterHydrate {
+    fn register_hydrate(&self);
+}
+
+// `Deserialize<'static>` rather than `DeserializeOwned` so that atoms holding
+// borrowed data still compile, they fail to deserialize and are initialized
+impl<T: 'static + Clone + Serialize + Deserialize<'static>> RegisterHydrate
+    for HydrateRegistrar<T>
+{
+    fn register_hydrate(&self) {
+        let id = self.id;
+        if reactive_state_exists_for_id::<Dehydrator>(id) {
+            return;
+        }
+        let key = match with_store(|store_refcell| store_refcell.borrow().debug_name_of(&id)) {
+            Some(key) => key,
+            None => return,
+        };
+
+        let hydrated_value =
+            with_store(|store_refcell| store_refcell.borrow_mut().hydrated_values.remove(&key));
+        if let Some(value) = hydrated_value {
+            if !reactive_state_exists_for_id::<T>(id) {
+                if let Ok(value) = T::deserialize(value) {
+                    set_inert_atom_state_with_id(value, id);
+                }
+            }
+        }
+
+        let dehydrator = Dehydrator {
+            key: key.into(),
+            serialize: Rc::new(move || {
+                serde_json::to_value(clone_reactive_state_with_id::<T>(id)?).ok()
+            }),
+        };
+        set_inert_atom_state_with_id(dehydrator, id);
+    }
+}
+
+pub trait SkipHydrate {
+    fn register_hydrate(&self);
+}
+
+impl<T> SkipHydrate for &HydrateRegistrar<T> {
+    fn register_hydrate(&self) {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{reactive_state_access::atom::Atom, *};
+    use std::cell::Cell;
+
+    thread_local! {
+        static INITIALIZER_RUNS: Cell<u32> = const { Cell::new(0) };
+    }
+
+    #[atom]
+    fn page_title() -> Atom<String> {
+        INITIALIZER_RUNS.with(|runs| runs.set(runs.get() + 1));
+        "Untitled".to_string()
+    }
+
+    #[atom]
+    fn todo_done(id: usize) -> Atom<bool> {
+        INITIALIZER_RUNS.with(|runs| runs.set(runs.get() + 1));
+        id == 0
+    }
+
+    #[test]
+    fn test_dehydrate_and_hydrate() {
+        let server = StoreHandle::new();
+        let json = server.enter(|| {
+            page_title().set("Todos".to_string());
+            todo_done(1).set(true);
+            todo_done(2);
+            dehydrate()
+        });
+        assert_eq!(INITIALIZER_RUNS.with(Cell::get), 3);
+
+        let client = StoreHandle::new();
+        client.enter(|| {
+            hydrate(&json).unwrap();
+            assert_eq!(page_title().get(), "Todos");
+            assert!(todo_done(1).get());
+            assert!(!todo_done(2).get());
+            assert_eq!(
+                INITIALIZER_RUNS.with(Cell::get),
+                3,
+                "initializers do not re-run"
+            );
+
+            page_title().reset_to_default();
+            assert_eq!(page_title().get(), "Untitled");
+        });
+    }
+
+    #[test]
+    fn test_stable_hash() {
+        assert_eq!(stable_hash(&7_usize), stable_hash(&7_u64));
+        assert_eq!(stable_hash(&(1_u32, "a")), 0xfa0b_bfc7_3a99_6080);
+    }
+}
@@ -4,7 +4,7 @@ use std::collections::HashMap;
 pub use std::collections::HashSet;
 // use seed::*;
 use crate::*;
-use std::{hash::Hash, rc::Rc};
+use std::{any::TypeId, cell::RefCell, hash::Hash, rc::Rc};
 
 #[derive(Debug, Clone)]
 pub struct ReactiveContext {
@@ -54,20 +54,191 @@ impl RxFunc {
     }
 }
 
+/// Compares the value stored for an id before and after it is written, so
+/// that writing back an equal value does not re-run dependent reactions.
+///
+/// Typically this is registered via `#[atom(memo)]` or `#[reaction(memo)]`.
+#[derive(Clone)]
+pub struct Memo {
+    pub snapshot: Rc<dyn Fn() -> Box<dyn FnOnce() -> bool>>,
+}
+
+impl Memo {
+    pub fn new<T: 'static + Clone + PartialEq>(id: StorageKey) -> Self {
+        Memo {
+            snapshot: Rc::new(move || {
+                let previous = clone_reactive_state_with_id::<T>(id);
+                Box::new(move || {
+                    reactive_state_functions::with_store(|store_refcell| {
+                        store_refcell.borrow().get_state_with_id::<T>(&id) != previous.as_ref()
+                    })
+                })
+            }),
+        }
+    }
+}
+
+/// The name of the function an atom or reaction was declared with.
+///
+/// Typically this is registered by the `#[atom]` and `#[reaction]` macros and
+/// used to make diagnostics readable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StateName(pub &'static str);
+
+/// What kind of reactive state is stored for an id.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NodeKind {
+    Atom,
+    Reaction,
+    Effect,
+    State,
+}
+
+/// Returned when adding a dependency would make the reactive graph cyclic.
+///
+/// `chain` lists every id involved, starting and ending with the same id,
+/// along with the function name it was declared with if known.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DependencyCycle {
+    pub chain: Vec<(StorageKey, Option<&'static str>)>,
+}
+
+impl std::fmt::Display for DependencyCycle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "reactive dependency cycle detected: ")?;
+        for (index, (key, name)) in self.chain.iter().enumerate() {
+            if index > 0 {
+                write!(f, " -> ")?;
+            }
+            match name {
+                Some(name) => write!(f, "{} ({:?})", name, key)?,
+                None => write!(f, "{:?}", key)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DependencyCycle {}
+
+/// A shared handle to a [`Store`].
+///
+/// Atoms, reactions and topological state used inside [`StoreHandle::enter`]
+/// live in that store only, so two handles never see each other's state. Use
+/// one per mounted app or widget, or per test to keep it hermetic.
+#[derive(Clone)]
+pub struct StoreHandle(pub(crate) Rc<RefCell<Store>>);
+
+impl StoreHandle {
+    pub fn new() -> StoreHandle {
+        StoreHandle(Rc::new(RefCell::new(Store::new())))
+    }
+
+    /// Runs `func` with this store in scope. Scopes nest, the innermost one
+    /// is used.
+    pub fn enter<F: FnOnce() -> R, R>(&self, func: F) -> R {
+        // leaves the scope even if `func` panics
+        struct LeaveScope;
+        impl Drop for LeaveScope {
+            fn drop(&mut self) {
+                reactive_state_functions::pop_store();
+            }
+        }
+
+        reactive_state_functions::push_store(self.clone());
+        let _leave_scope = LeaveScope;
+        func()
+    }
+}
+
+impl Default for StoreHandle {
+    fn default() -> Self {
+        StoreHandle::new()
+    }
+}
+
+// removes the entry for a key from one of the secondary maps in the anymap
+type SecondaryMapRemover = fn(&mut anymap::Map<dyn Any>, DefaultKey);
+
 pub struct Store {
     pub id_to_key_map: HashMap<StorageKey, DefaultKey>,
     pub primary_slotmap: DenseSlotMap<DefaultKey, StorageKey>,
     pub anymap: anymap::Map<dyn Any>,
     pub unseen_ids: HashSet<TopoKey>,
+    pub batch_depth: usize,
+    pub batched_ids: Vec<StorageKey>,
+    /// The cursor of each undo history when the current batch first recorded
+    /// a change in it.
+    pub batch_undo_cursors: Vec<(StorageKey, usize)>,
+    /// The value of each atom before its first write in each of the enclosing
+    /// transactions, innermost last.
+    pub transaction_journals: Vec<Snapshot>,
+    pub lazy_ids: HashSet<StorageKey>,
+    pub dirty_ids: HashSet<StorageKey>,
+    pub running_ids: Vec<StorageKey>,
+    pub propagation_depth: usize,
+    pub pending_effects: Vec<StorageKey>,
+    pub running_effects: bool,
+    pub next_effect_slot: u64,
+    pub next_collection_slot: u64,
+    pub unseen_reactive_ids: HashSet<StorageKey>,
+    pub kept_alive_ids: HashSet<StorageKey>,
+    secondarymap_removers: Vec<SecondaryMapRemover>,
+    pub storage_backend: Rc<dyn StorageBackend>,
+    pub hydrated_values: serde_json::Map<String, serde_json::Value>,
+    pub spawner: Option<Spawner>,
+    pub write_middleware: Vec<(u64, WriteMiddleware)>,
+    pub next_middleware_id: u64,
+    pub value_formatters: HashMap<TypeId, ValueFormatter>,
+    pub value_cloners: HashMap<TypeId, ValueCloner>,
 }
 
 impl Store {
+    /// Runs `func` with a new, empty store in scope, see [`StoreHandle`].
+    ///
+    /// ```
+    /// use atomic_hooks::{atom::Atom, *};
+    /// #[atom]
+    /// fn count() -> Atom<i32> {
+    ///     0
+    /// }
+    ///
+    /// count().set(1);
+    /// store::Store::scope(|| assert_eq!(count().get(), 0));
+    /// assert_eq!(count().get(), 1);
+    /// ```
+    pub fn scope<F: FnOnce() -> R, R>(func: F) -> R {
+        StoreHandle::new().enter(func)
+    }
+
     pub(crate) fn new() -> Store {
         Store {
             id_to_key_map: HashMap::new(),
             primary_slotmap: DenseSlotMap::new(),
             anymap: anymap::Map::new(),
             unseen_ids: HashSet::new(),
+            batch_depth: 0,
+            batched_ids: vec![],
+            batch_undo_cursors: vec![],
+            transaction_journals: vec![],
+            lazy_ids: HashSet::new(),
+            dirty_ids: HashSet::new(),
+            running_ids: vec![],
+            propagation_depth: 0,
+            pending_effects: vec![],
+            running_effects: false,
+            next_effect_slot: 0,
+            next_collection_slot: 0,
+            unseen_reactive_ids: HashSet::new(),
+            kept_alive_ids: HashSet::new(),
+            secondarymap_removers: vec![],
+            storage_backend: Rc::new(MemoryBackend::new()),
+            hydrated_values: serde_json::Map::new(),
+            spawner: None,
+            write_middleware: vec![],
+            next_middleware_id: 0,
+            value_formatters: middleware::std_value_formatters(),
+            value_cloners: middleware::std_value_cloners(),
         }
     }
 
@@ -76,9 +247,10 @@ impl Store {
         if let Some(map) = self.get_mut_secondarymap::<RxFunc>() {
             map.insert(key, func);
         } else {
-            let mut sm: SecondaryMap<DefaultKey, RxFunc> = SecondaryMap::new();
-            sm.insert(key, func);
-            self.anymap.insert(sm);
+            self.register_secondarymap::<RxFunc>();
+            self.get_mut_secondarymap::<RxFunc>()
+                .unwrap()
+                .insert(key, func);
         }
     }
 
@@ -94,10 +266,46 @@ impl Store {
         let dep_sm_key = self.id_to_key_map.get(id).unwrap().clone();
         let map = self.responsive_map();
         map.insert(dep_sm_key, vec![]);
+        self.set_state_with_id(NodeKind::Atom, id);
+    }
+
+    /// Returns the kind of reactive state stored for `id`. Topological state
+    /// is always a `NodeKind::State`.
+    pub fn kind_of(&self, id: &StorageKey) -> Option<NodeKind> {
+        match id {
+            StorageKey::TopoKey(_) => Some(NodeKind::State),
+            StorageKey::SlottedKey(_) => self.get_state_with_id::<NodeKind>(id).copied(),
+        }
+    }
+
+    /// Returns every dependency edge as `(source, dependent)` pairs.
+    pub fn dependency_edges(&self) -> Vec<(StorageKey, StorageKey)> {
+        self.get_secondarymap::<Vec<DefaultKey>>()
+            .map(|map| {
+                map.iter()
+                    .filter_map(|(source_key, reaction_keys)| {
+                        self.primary_slotmap
+                            .get(source_key)
+                            .map(|source| (*source, reaction_keys))
+                    })
+                    .flat_map(|(source, reaction_keys)| {
+                        reaction_keys.iter().filter_map(move |key| {
+                            self.primary_slotmap
+                                .get(*key)
+                                .map(|reaction| (source, *reaction))
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
     }
 
     pub fn remove_dependency(&mut self, source_id: &StorageKey, reaction_id: &StorageKey) {
-        let source_sm_key = self.id_to_key_map.get(source_id).unwrap().clone();
+        // a source that has been removed took its dependents with it
+        let source_sm_key = match self.id_to_key_map.get(source_id) {
+            Some(key) => *key,
+            None => return,
+        };
         let reaction_sm_key = self.id_to_key_map.get(reaction_id).unwrap().clone();
 
         let map = &mut self.responsive_map();
@@ -109,18 +317,141 @@ impl Store {
         }
     }
 
+    /// Records that `reaction_id` observes `source_id`.
+    ///
+    /// Panics with the chain of ids involved if this would make `source_id`
+    /// depend on itself, see [`Store::try_add_dependency`].
     pub fn add_dependency(&mut self, source_id: &StorageKey, reaction_id: &StorageKey) {
-        let source_sm_key = self.id_to_key_map.get(source_id).unwrap().clone();
-        let reaction_sm_key = self.id_to_key_map.get(reaction_id).unwrap().clone();
+        if let Err(cycle) = self.try_add_dependency(source_id, reaction_id) {
+            panic!("{}", cycle)
+        }
+    }
+
+    /// Records that `reaction_id` observes `source_id`, or returns the cycle
+    /// that adding this dependency would create.
+    pub fn try_add_dependency(
+        &mut self,
+        source_id: &StorageKey,
+        reaction_id: &StorageKey,
+    ) -> Result<(), DependencyCycle> {
+        let source_sm_key = *self.id_to_key_map.get(source_id).unwrap();
+        let reaction_sm_key = *self.id_to_key_map.get(reaction_id).unwrap();
+
+        if let Some(nodes) = self.responsive_map().get(source_sm_key) {
+            if nodes.contains(&reaction_sm_key) {
+                return Ok(());
+            }
+        }
+
+        if let Some(path) = self.dependency_path(reaction_sm_key, source_sm_key) {
+            let mut chain = vec![*source_id];
+            chain.extend(
+                path.iter()
+                    .filter_map(|key| self.primary_slotmap.get(*key).copied()),
+            );
+            return Err(self.dependency_cycle(chain));
+        }
 
         let map = &mut self.responsive_map();
         if let Some(nodes) = map.get_mut(source_sm_key) {
-            if !nodes.contains(&reaction_sm_key) {
-                nodes.push(reaction_sm_key)
-            }
+            nodes.push(reaction_sm_key)
         } else {
             map.insert(source_sm_key, vec![reaction_sm_key]);
         }
+        Ok(())
+    }
+
+    /// Removes every dependency edge leading to `reaction_id`.
+    pub fn remove_dependencies_of(&mut self, reaction_id: &StorageKey) {
+        if let Some(reaction_sm_key) = self.id_to_key_map.get(reaction_id).copied() {
+            for (_, nodes) in self.responsive_map().iter_mut() {
+                nodes.retain(|n| *n != reaction_sm_key);
+            }
+        }
+    }
+
+    /// Returns the name `id` was declared with, if it has been registered.
+    pub fn name_of(&self, id: &StorageKey) -> Option<&'static str> {
+        self.get_state_with_id::<StateName>(id).map(|name| name.0)
+    }
+
+    /// Returns the name `id` was declared with, followed by the hash of its
+    /// arguments if it has any, i.e. `app::user#00000000000003e8`.
+    pub fn debug_name_of(&self, id: &StorageKey) -> Option<String> {
+        let name = self.name_of(id)?;
+        Some(match id {
+            StorageKey::SlottedKey(SlottedKey { slot, .. }) if *slot != 0 => {
+                format!("{}#{:016x}", name, slot)
+            }
+            _ => name.to_string(),
+        })
+    }
+
+    /// Returns the id whose [`Store::debug_name_of`] is `debug_name`.
+    pub fn id_by_debug_name(&self, debug_name: &str) -> Option<StorageKey> {
+        let (name, slot) = match debug_name.split_once('#') {
+            Some((name, args_hash)) => (name, u64::from_str_radix(args_hash, 16).ok()?),
+            None => (debug_name, 0),
+        };
+        let id = StorageKey::SlottedKey(SlottedKey {
+            location: stable_hash(name),
+            slot,
+        });
+        if self.name_of(&id) == Some(name) {
+            Some(id)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn dependency_cycle(&self, chain: Vec<StorageKey>) -> DependencyCycle {
+        DependencyCycle {
+            chain: chain
+                .into_iter()
+                .map(|key| (key, self.name_of(&key)))
+                .collect(),
+        }
+    }
+
+    // finds a path of dependents leading from `from` to `to`, both included
+    fn dependency_path(&self, from: DefaultKey, to: DefaultKey) -> Option<Vec<DefaultKey>> {
+        let mut visited = HashSet::new();
+        let mut path = vec![];
+        if self.find_dependency_path(from, to, &mut visited, &mut path) {
+            Some(path)
+        } else {
+            None
+        }
+    }
+
+    fn find_dependency_path(
+        &self,
+        key: DefaultKey,
+        to: DefaultKey,
+        visited: &mut HashSet<DefaultKey>,
+        path: &mut Vec<DefaultKey>,
+    ) -> bool {
+        if !visited.insert(key) {
+            return false;
+        }
+        path.push(key);
+        if key == to {
+            return true;
+        }
+
+        if let Some(reaction_keys) = self
+            .get_secondarymap::<Vec<DefaultKey>>()
+            .and_then(|map| map.get(key))
+        {
+            for reaction_key in reaction_keys {
+                if self.find_dependency_path(*reaction_key, to, visited, path) {
+                    return true;
+                }
+            }
+        }
+
+        path.pop();
+        false
     }
 
     pub(crate) fn state_exists_with_id<T: 'static>(&self, id: StorageKey) -> bool {
@@ -166,27 +497,67 @@ impl Store {
         }
     }
 
-    pub(crate) fn clone_dep_funcs_for_id(&mut self, id: &StorageKey) -> Vec<(StorageKey, RxFunc)> {
-        let reaction_keys = self.get_state_with_id::<Vec<DefaultKey>>(id).cloned();
+    /// Returns every reaction that (transitively) depends on one of the given
+    /// ids, in topological order. Each reaction appears exactly once and only
+    /// after all of the reactions it observes, so running them in this order
+    /// never exposes a half-updated value.
+    pub(crate) fn clone_dep_funcs_in_order(&self, ids: &[StorageKey]) -> Vec<(StorageKey, RxFunc)> {
+        let mut visited = HashSet::new();
+        let mut post_order = vec![];
+
+        for id in ids {
+            if let Some(reaction_keys) = self.get_state_with_id::<Vec<DefaultKey>>(id) {
+                for key in reaction_keys {
+                    self.visit_dependents(*key, &mut visited, &mut post_order);
+                }
+            }
+        }
 
-        if let Some(reaction_keys) = &reaction_keys {
-            reaction_keys
-                .iter()
-                .filter_map(|key| {
-                    if let Some(existing_secondary_map) = self.get_mut_secondarymap::<RxFunc>() {
-                        if let Some(reaction) = existing_secondary_map.get(*key).cloned() {
-                            Some((self.primary_slotmap.get(*key).unwrap().clone(), reaction))
-                        } else {
-                            panic!("cannot find {:#?} for id {:#?}", key, id);
-                        }
-                    } else {
-                        None
-                    }
-                })
-                .collect::<Vec<(StorageKey, RxFunc)>>()
-        } else {
-            vec![]
+        post_order
+            .iter()
+            .rev()
+            .map(|key| {
+                let reaction = self
+                    .get_secondarymap::<RxFunc>()
+                    .and_then(|existing_secondary_map| existing_secondary_map.get(*key))
+                    .cloned()
+                    .unwrap_or_else(|| panic!("cannot find {:#?} for ids {:#?}", key, ids));
+                (*self.primary_slotmap.get(*key).unwrap(), reaction)
+            })
+            .collect::<Vec<(StorageKey, RxFunc)>>()
+    }
+
+    pub(crate) fn dependent_ids(&self, id: &StorageKey) -> Vec<StorageKey> {
+        self.get_state_with_id::<Vec<DefaultKey>>(id)
+            .map(|reaction_keys| {
+                reaction_keys
+                    .iter()
+                    .filter_map(|key| self.primary_slotmap.get(*key).copied())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn visit_dependents(
+        &self,
+        key: DefaultKey,
+        visited: &mut HashSet<DefaultKey>,
+        post_order: &mut Vec<DefaultKey>,
+    ) {
+        if !visited.insert(key) {
+            return;
+        }
+
+        if let Some(reaction_keys) = self
+            .get_secondarymap::<Vec<DefaultKey>>()
+            .and_then(|map| map.get(key))
+        {
+            for reaction_key in reaction_keys {
+                self.visit_dependents(*reaction_key, visited, post_order);
+            }
         }
+
+        post_order.push(key);
     }
 
     pub(crate) fn set_state_with_id<T: 'static>(&mut self, data: T, current_id: &StorageKey) {
@@ -225,6 +596,70 @@ impl Store {
     pub fn register_secondarymap<T: 'static>(&mut self) {
         let sm: SecondaryMap<DefaultKey, T> = SecondaryMap::new();
         self.anymap.insert(sm);
+        self.secondarymap_removers
+            .push(remove_from_secondarymap::<T>);
+    }
+
+    /// Returns the reactive ids that have not been seen since the unseen list
+    /// was last reset and that no seen reaction or effect still observes.
+    pub(crate) fn collectable_ids(&self) -> Vec<StorageKey> {
+        let mut sources_of = HashMap::<StorageKey, Vec<StorageKey>>::new();
+        for (source, dependent) in self.dependency_edges() {
+            sources_of.entry(dependent).or_default().push(source);
+        }
+
+        let mut kept = HashSet::new();
+        let mut to_visit = self
+            .id_to_key_map
+            .keys()
+            .filter(|id| {
+                !self.unseen_reactive_ids.contains(id)
+                    || self.kept_alive_ids.contains(id)
+                    || self.kind_of(id) == Some(NodeKind::Effect)
+            })
+            .copied()
+            .collect::<Vec<_>>();
+
+        // everything a kept id observes has to be kept as well
+        while let Some(id) = to_visit.pop() {
+            if kept.insert(id) {
+                if let Some(sources) = sources_of.get(&id) {
+                    to_visit.extend(sources.iter().copied());
+                }
+            }
+        }
+
+        self.unseen_reactive_ids
+            .iter()
+            .filter(|id| !kept.contains(id) && self.id_to_key_map.contains_key(id))
+            .copied()
+            .collect()
+    }
+
+    /// Removes `id`, all of its state and every dependency edge it is part
+    /// of from the store.
+    pub(crate) fn remove_id(&mut self, id: &StorageKey) {
+        self.remove_dependencies_of(id);
+        if let Some(key) = self.id_to_key_map.remove(id) {
+            for remover in &self.secondarymap_removers {
+                remover(&mut self.anymap, key);
+            }
+            self.primary_slotmap.remove(key);
+        }
+        self.unseen_reactive_ids.remove(id);
+        self.kept_alive_ids.remove(id);
+        self.lazy_ids.remove(id);
+        self.dirty_ids.remove(id);
+    }
+
+    /// Inserts a key for `id` if it does not have one yet and marks it as
+    /// seen, so that it is kept by the next `purge_unseen_reactive_ids`.
+    pub(crate) fn insert_seen_id(&mut self, id: StorageKey) {
+        self.unseen_reactive_ids.remove(&id);
+        if !self.id_to_key_map.contains_key(&id) {
+            let key = self.primary_slotmap.insert(id);
+            self.id_to_key_map.insert(id, key);
+        }
     }
 
     pub fn return_key_for_type_and_insert_if_required<T: 'static + Clone + Eq + Hash>(
@@ -232,6 +667,8 @@ impl Store {
         id: StorageKey,
         value: T,
     ) -> StorageKey {
+        self.unseen_reactive_ids.remove(&id);
+
         //unwrap or default to keep borrow checker happy
         let key = self.id_to_key_map.get(&id).copied().unwrap_or_default();
 
@@ -272,3 +709,9 @@ impl Store {
         }
     }
 }
+
+fn remove_from_secondarymap<T: 'static>(anymap: &mut anymap::Map<dyn Any>, key: DefaultKey) {
+    if let Some(map) = anymap.get_mut::<SecondaryMap<DefaultKey, T>>() {
+        map.remove(key);
+    }
+}
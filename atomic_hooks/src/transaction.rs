@@ -0,0 +1,196 @@
+use crate::{
+    reactive_state_functions::{batch, with_store},
+    reverse::{discard_undo_commands_since, undo_cursors_in_batch},
+    snapshot::{restore, Snapshot},
+    store::StorageKey,
+};
+use std::panic::{self, AssertUnwindSafe};
+
+/// Runs `func` as a single change to the state: if it returns `Ok`, every atom
+/// written inside is kept, dependent reactions re-run once and reversible
+/// changes are recorded as a single undo entry. If it returns `Err` or
+/// panics, every atom it wrote is rolled back to its previous value and its
+/// reversible changes are discarded.
+///
+/// Only atoms whose type is `Clone` are rolled back. Transactions nest, an
+/// inner transaction that succeeds is rolled back with its outer one.
+///
+/// ```
+/// use atomic_hooks::{atom::Atom, *};
+/// #[atom]
+/// fn todo() -> Atom<Vec<String>> {
+///     vec!["write docs".to_string()]
+/// }
+///
+/// #[atom]
+/// fn done() -> Atom<Vec<String>> {
+///     vec![]
+/// }
+///
+/// let moved: Result<(), &str> = transaction(|| {
+///     let mut item = None;
+///     todo().update(|todo| item = Some(todo.remove(0)));
+///     done().update(|done| done.extend(item));
+///     Err("the done list is read only")
+/// });
+///
+/// assert!(moved.is_err());
+/// assert_eq!(todo().get(), vec!["write docs".to_string()]);
+/// assert!(done().get().is_empty());
+/// ```
+pub fn transaction<F, R, E>(func: F) -> Result<R, E>
+where
+    F: FnOnce() -> Result<R, E>,
+{
+    // a panic is resumed after the batch, so that the batch still ends
+    let result = batch(|| {
+        let undo_cursors = undo_cursors_in_batch();
+        let batched_len = with_store(|store_refcell| {
+            let mut store = store_refcell.borrow_mut();
+            store.transaction_journals.push(Snapshot::default());
+            store.batched_ids.len()
+        });
+
+        let result = panic::catch_unwind(AssertUnwindSafe(func));
+
+        let journal =
+            with_store(|store_refcell| store_refcell.borrow_mut().transaction_journals.pop())
+                .expect("the journal of the transaction should be present");
+        match &result {
+            Ok(Ok(_)) => with_store(|store_refcell| {
+                if let Some(outer_journal) =
+                    store_refcell.borrow_mut().transaction_journals.last_mut()
+                {
+                    outer_journal.merge(journal);
+                }
+            }),
+            _ => {
+                // the atoms are back as they were, so nothing has to re-run
+                restore(&journal);
+                with_store(|store_refcell| {
+                    store_refcell.borrow_mut().batched_ids.truncate(batched_len)
+                });
+                discard_undo_commands_since(undo_cursors);
+            }
+        }
+        result
+    });
+
+    match result {
+        Ok(result) => result,
+        Err(panic) => panic::resume_unwind(panic),
+    }
+}
+
+/// Captures the value of the atom `id` before it is written, so that the
+/// enclosing [`transaction`] can roll it back.
+pub fn journal_write(id: StorageKey) {
+    let journal = with_store(|store_refcell| store_refcell.borrow_mut().transaction_journals.pop());
+    if let Some(mut journal) = journal {
+        journal.capture(id);
+        with_store(|store_refcell| {
+            store_refcell
+                .borrow_mut()
+                .transaction_journals
+                .push(journal)
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        reactive_state_access::{atom::Atom, reaction::Reaction, reversible_atom::ReversibleAtom},
+        *,
+    };
+
+    #[atom]
+    fn checking() -> Atom<i32> {
+        100
+    }
+
+    #[atom(reversible)]
+    fn savings() -> ReversibleAtom<i32> {
+        0
+    }
+
+    #[reaction]
+    fn balance() -> Reaction<i32> {
+        checking().observe() + savings().observe()
+    }
+
+    fn move_to_savings(amount: i32) -> Result<(), String> {
+        checking().update(|checking| *checking -= amount);
+        savings().update(|savings| *savings += amount);
+        if checking().get() < 0 {
+            return Err("insufficient funds".to_string());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_transaction_commits_and_rolls_back() {
+        let balance = balance();
+        let changes = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let subscribed_changes = changes.clone();
+        let _subscription = balance.subscribe(move |_, new| {
+            subscribed_changes.borrow_mut().push(*new);
+        });
+
+        assert_eq!(transaction(|| move_to_savings(30)), Ok(()));
+        assert_eq!((checking().get(), savings().get()), (70, 30));
+        assert_eq!(global_reverse_queue().len(), 1);
+
+        assert!(transaction(|| move_to_savings(100)).is_err());
+        assert_eq!((checking().get(), savings().get()), (70, 30));
+        assert_eq!(
+            global_reverse_queue().len(),
+            1,
+            "undo entries are discarded"
+        );
+        assert_eq!(
+            *changes.borrow(),
+            vec![100],
+            "a rolled back transaction does not re-run reactions"
+        );
+
+        global_reverse_queue().travel_backwards();
+        assert_eq!(savings().get(), 0);
+    }
+
+    #[test]
+    fn test_transaction_rolls_back_on_panic() {
+        let result = std::panic::catch_unwind(|| {
+            transaction(|| -> Result<(), ()> {
+                checking().set(0);
+                transaction(|| -> Result<(), ()> {
+                    savings().set(100);
+                    Ok(())
+                })?;
+                panic!("interrupted")
+            })
+        });
+        assert!(result.is_err());
+        assert_eq!((checking().get(), savings().get()), (100, 0));
+        assert!(!global_reverse_queue().can_undo());
+
+        checking().set(5);
+        assert_eq!(balance().get(), 5, "the store is usable after a panic");
+    }
+
+    #[test]
+    fn test_rollback_is_not_vetoed() {
+        let middleware = add_write_middleware(|write| write.new.as_deref() != Some("100"));
+        let result = transaction(|| -> Result<(), ()> {
+            checking().set(40);
+            savings().set(60);
+            Err(())
+        });
+        remove_write_middleware(middleware);
+
+        assert!(result.is_err());
+        assert_eq!((checking().get(), savings().get()), (100, 0));
+        assert_eq!(balance().get(), 100);
+    }
+}
@@ -2,11 +2,15 @@ mod ev_handlers;
 
 
 mod seed_bind;
+mod spawn;
+mod storage;
 mod update_el;
 mod utils;
 mod reactive_enhancements;
 pub use ev_handlers::StateAccessEventHandlers;
 pub use seed_bind::{UpdateElLocal, InputBind};
+pub use spawn::spawn_resources_locally;
+pub use storage::{LocalStorage, SessionStorage};
 pub use update_el::{StateAccessUpdateEl, LocalUpdateEl2};
 pub use utils::{
     after_render, after_render_once, get_html_element_by_id, //handle_unmount,
@@ -2,6 +2,7 @@ use atomic_hooks::Observable;
 use seed::{prelude::*, *};
 use atomic_hooks::state_access::StateAccess;
 use atomic_hooks::atom::Atom;
+use atomic_hooks::{Lens, Selector};
 
 pub trait UpdateElLocal<T> {
     fn update_el(self, el: &mut T);
@@ -48,3 +49,31 @@ impl <Ms,T> InputBind<Ms,T>   for Atom<T>   where Ms: 'static, T: 'static+ std::
     }
 }
 
+
+impl <Ms,T> InputBind<Ms,T>   for Selector<T>   where Ms: 'static, T: 'static+ std::str::FromStr + std::fmt::Display {
+    fn bind( self, attr: At) -> (seed::virtual_dom::attrs::Attrs, seed::EventHandler<Ms>){
+        let val_disp = self.observe_with(|v| format!("{}", v));
+            (
+            attrs!(attr => val_disp),
+            input_ev(Ev::Input, move |ev| {
+                if let Ok(parsed_type) = ev.parse::<T>() {
+                    self.set(parsed_type);
+                }
+            }),
+        )
+    }
+}
+
+impl <Ms,S,T> InputBind<Ms,T>   for Lens<S,T>   where Ms: 'static, S: 'static + Clone, T: 'static+ Clone + PartialEq + std::str::FromStr + std::fmt::Display {
+    fn bind( self, attr: At) -> (seed::virtual_dom::attrs::Attrs, seed::EventHandler<Ms>){
+        let val_disp = self.observe_with(|v| format!("{}", v));
+            (
+            attrs!(attr => val_disp),
+            input_ev(Ev::Input, move |ev| {
+                if let Ok(parsed_type) = ev.parse::<T>() {
+                    self.set(parsed_type);
+                }
+            }),
+        )
+    }
+}
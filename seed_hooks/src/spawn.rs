@@ -0,0 +1,12 @@
+use atomic_hooks::{set_spawner, LocalFuture};
+
+/// Runs the futures of `resource` reactions in the current store with
+/// `wasm_bindgen_futures::spawn_local`. Call it once when the app starts.
+///
+/// ```ignore
+/// spawn_resources_locally();
+/// let user = resource(|| fetch_user(user_id().observe()));
+/// ```
+pub fn spawn_resources_locally() {
+    set_spawner(|future: LocalFuture| wasm_bindgen_futures::spawn_local(future));
+}
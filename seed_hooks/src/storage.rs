@@ -0,0 +1,57 @@
+use atomic_hooks::StorageBackend;
+
+/// Persists `#[atom(persist = "key")]` atoms in the browser's localStorage.
+///
+/// ```ignore
+/// set_storage_backend(LocalStorage);
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LocalStorage;
+
+/// Persists `#[atom(persist = "key")]` atoms in the browser's sessionStorage.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SessionStorage;
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+fn session_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.session_storage().ok()?
+}
+
+impl StorageBackend for LocalStorage {
+    fn get_item(&self, key: &str) -> Option<String> {
+        local_storage()?.get_item(key).ok()?
+    }
+
+    fn set_item(&self, key: &str, value: &str) {
+        if let Some(storage) = local_storage() {
+            let _ = storage.set_item(key, value);
+        }
+    }
+
+    fn remove_item(&self, key: &str) {
+        if let Some(storage) = local_storage() {
+            let _ = storage.remove_item(key);
+        }
+    }
+}
+
+impl StorageBackend for SessionStorage {
+    fn get_item(&self, key: &str) -> Option<String> {
+        session_storage()?.get_item(key).ok()?
+    }
+
+    fn set_item(&self, key: &str, value: &str) {
+        if let Some(storage) = session_storage() {
+            let _ = storage.set_item(key, value);
+        }
+    }
+
+    fn remove_item(&self, key: &str) {
+        if let Some(storage) = session_storage() {
+            let _ = storage.remove_item(key);
+        }
+    }
+}